@@ -0,0 +1,144 @@
+use core::str::FromStr;
+
+// a single `key=value` or bare `key` from the command line
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Arg<'a> {
+    pub key: &'a str,
+    pub value: Option<&'a str>,
+}
+
+// an argument that was given but couldnt be parsed as the type that was asked for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ArgError<'a> {
+    pub key: &'a str,
+    pub value: Option<&'a str>,
+}
+
+// the command line as whitespace separated `key=value` pairs, values can be quoted to include spaces
+// when a key is given more than once the last one wins
+#[derive(Debug, Clone, Copy)]
+pub struct KernelArgs<'a> {
+    command_line: &'a str,
+}
+
+impl<'a> KernelArgs<'a> {
+    pub const fn new(command_line: &'a str) -> Self {
+        Self { command_line }
+    }
+
+    pub fn command_line(&self) -> &'a str {
+        self.command_line
+    }
+
+    pub fn iter(&self) -> KernelArgsIter<'a> {
+        KernelArgsIter {
+            remaining: self.command_line,
+        }
+    }
+
+    pub fn get(&self, key: &str) -> Option<Arg<'a>> {
+        self.iter().filter(|arg| arg.key == key).last()
+    }
+
+    pub fn value(&self, key: &str) -> Option<&'a str> {
+        self.get(key)?.value
+    }
+
+    // a bare `key` counts as true
+    pub fn flag(&self, key: &str) -> Result<bool, ArgError<'a>> {
+        let Some(arg) = self.get(key) else {
+            return Ok(false);
+        };
+        match arg.value {
+            None | Some("true" | "1" | "yes" | "on") => Ok(true),
+            Some("false" | "0" | "no" | "off") => Ok(false),
+            Some(_) => Err(ArgError {
+                key: arg.key,
+                value: arg.value,
+            }),
+        }
+    }
+
+    // `Ok(None)` if the key wasnt given at all
+    pub fn parse<T: FromStr>(&self, key: &str) -> Result<Option<T>, ArgError<'a>> {
+        let Some(arg) = self.get(key) else {
+            return Ok(None);
+        };
+        arg.value
+            .and_then(|value| value.parse().ok())
+            .map(Some)
+            .ok_or(ArgError {
+                key: arg.key,
+                value: arg.value,
+            })
+    }
+}
+
+impl<'a> IntoIterator for &KernelArgs<'a> {
+    type Item = Arg<'a>;
+    type IntoIter = KernelArgsIter<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+// a number of bytes, with an optional `K`, `M` or `G` suffix
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ByteSize(pub usize);
+
+impl FromStr for ByteSize {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (number, shift) = match s.as_bytes().last() {
+            Some(b'K' | b'k') => (&s[..s.len() - 1], 10),
+            Some(b'M' | b'm') => (&s[..s.len() - 1], 20),
+            Some(b'G' | b'g') => (&s[..s.len() - 1], 30),
+            _ => (s, 0),
+        };
+        let number = number.parse::<usize>().map_err(|_| ())?;
+        number.checked_mul(1 << shift).map(Self).ok_or(())
+    }
+}
+
+pub struct KernelArgsIter<'a> {
+    remaining: &'a str,
+}
+
+impl<'a> Iterator for KernelArgsIter<'a> {
+    type Item = Arg<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let remaining = self.remaining.trim_start();
+        if remaining.is_empty() {
+            self.remaining = remaining;
+            return None;
+        }
+
+        let key_end = remaining
+            .find(|c: char| c == '=' || c.is_whitespace())
+            .unwrap_or(remaining.len());
+        let key = &remaining[..key_end];
+        let Some(rest) = remaining[key_end..].strip_prefix('=') else {
+            self.remaining = &remaining[key_end..];
+            return Some(Arg { key, value: None });
+        };
+
+        let (value, rest) = if let Some(quoted) = rest.strip_prefix('"') {
+            // an unterminated quote takes the rest of the line
+            match quoted.find('"') {
+                Some(end) => (&quoted[..end], &quoted[end + 1..]),
+                None => (quoted, ""),
+            }
+        } else {
+            let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+            (&rest[..end], &rest[end..])
+        };
+        self.remaining = rest;
+        Some(Arg {
+            key,
+            value: Some(value),
+        })
+    }
+}
@@ -0,0 +1,99 @@
+#![no_std]
+
+use crate::kernel_args::KernelArgs;
+use core::num::NonZeroUsize;
+
+pub mod kernel_args;
+
+// memory the bootloader hands over to the kernel, these are in the range the uefi spec reserves for os loaders
+pub const KERNEL_CODE: efi::MemoryType = efi::MemoryType(0x8000_0000);
+pub const KERNEL_DATA: efi::MemoryType = efi::MemoryType(0x8000_0001);
+
+// every format has 32 bit pixels
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(C)]
+pub enum PixelFormat {
+    Rgb,
+    Bgr,
+    // `reserved` only matters for working out how big a pixel is
+    Bitmask {
+        red: u32,
+        green: u32,
+        blue: u32,
+        reserved: u32,
+    },
+}
+
+impl PixelFormat {
+    // the bits of a pixel that hold the red, green and blue channels
+    pub const fn masks(self) -> [u32; 3] {
+        match self {
+            Self::Rgb => [0x0000FF, 0x00FF00, 0xFF0000],
+            Self::Bgr => [0xFF0000, 0x00FF00, 0x0000FF],
+            Self::Bitmask {
+                red, green, blue, ..
+            } => [red, green, blue],
+        }
+    }
+
+    // for bitmask formats the highest bit in any of the masks decides it, so 16 and 24 bit pixels are possible
+    pub const fn bytes_per_pixel(self) -> usize {
+        match self {
+            Self::Rgb | Self::Bgr => 4,
+            Self::Bitmask {
+                red,
+                green,
+                blue,
+                reserved,
+            } => (u32::BITS - (red | green | blue | reserved).leading_zeros()).div_ceil(8) as usize,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+#[repr(C)]
+pub struct FramebufferInfo {
+    pub base: usize,
+    // in bytes
+    pub size: usize,
+    pub width: usize,
+    pub height: usize,
+    pub pixels_per_scanline: usize,
+    pub format: PixelFormat,
+}
+
+// everything the kernel gets from the bootloader, it is passed to the kernel entry point in `rdi`
+#[repr(C)]
+pub struct BootInfo {
+    pub version: u32,
+    pub memory_map: efi::MemoryMap,
+    pub framebuffer: FramebufferInfo,
+    // the physical address of the acpi rsdp, if the firmware has one
+    pub rsdp: Option<NonZeroUsize>,
+    pub command_line: *const u8,
+    pub command_line_length: usize,
+}
+
+unsafe impl Send for BootInfo {}
+unsafe impl Sync for BootInfo {}
+
+impl BootInfo {
+    // bumped whenever the layout of anything in here changes, so a kernel and bootloader from different builds can tell
+    pub const VERSION: u32 = 3;
+
+    pub fn command_line(&self) -> &str {
+        if self.command_line.is_null() {
+            return "";
+        }
+        let bytes =
+            unsafe { core::slice::from_raw_parts(self.command_line, self.command_line_length) };
+        core::str::from_utf8(bytes).unwrap_or("")
+    }
+
+    pub fn kernel_args(&self) -> KernelArgs<'_> {
+        KernelArgs::new(self.command_line())
+    }
+}
+
+// the signature of the kernel entry point
+pub type KernelEntry = unsafe extern "sysv64" fn(boot_info: &'static BootInfo) -> !;
@@ -0,0 +1,260 @@
+use core::ops::Range;
+
+const PAGE_SIZE: usize = 4096;
+
+const ELF_MAGIC: [u8; 4] = *b"\x7FELF";
+const ELF_CLASS_64: u8 = 2;
+const ELF_DATA_LITTLE_ENDIAN: u8 = 1;
+const ELF_TYPE_DYNAMIC: u16 = 3;
+const ELF_MACHINE_X86_64: u16 = 62;
+
+const PROGRAM_TYPE_LOAD: u32 = 1;
+const PROGRAM_TYPE_DYNAMIC: u32 = 2;
+
+const DYNAMIC_TAG_NULL: i64 = 0;
+const DYNAMIC_TAG_RELA: i64 = 7;
+const DYNAMIC_TAG_RELA_SIZE: i64 = 8;
+const DYNAMIC_TAG_RELA_ENTRY_SIZE: i64 = 9;
+const DYNAMIC_TAG_REL: i64 = 17;
+const DYNAMIC_TAG_RELR: i64 = 36;
+
+const RELOCATION_NONE: u32 = 0;
+const RELOCATION_RELATIVE: u32 = 8;
+
+#[derive(Debug, Clone, Copy)]
+#[repr(C)]
+struct Header {
+    ident: [u8; 16],
+    kind: u16,
+    machine: u16,
+    version: u32,
+    entry: u64,
+    program_header_offset: u64,
+    section_header_offset: u64,
+    flags: u32,
+    header_size: u16,
+    program_header_size: u16,
+    program_header_count: u16,
+    section_header_size: u16,
+    section_header_count: u16,
+    section_name_index: u16,
+}
+
+#[derive(Debug, Clone, Copy)]
+#[repr(C)]
+struct ProgramHeader {
+    kind: u32,
+    flags: u32,
+    offset: u64,
+    virtual_address: u64,
+    physical_address: u64,
+    file_size: u64,
+    memory_size: u64,
+    align: u64,
+}
+
+#[derive(Debug, Clone, Copy)]
+#[repr(C)]
+struct Dynamic {
+    tag: i64,
+    value: u64,
+}
+
+#[derive(Debug, Clone, Copy)]
+#[repr(C)]
+struct Rela {
+    offset: u64,
+    info: u64,
+    addend: i64,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum ElfError {
+    Truncated,
+    NotElf,
+    Not64Bit,
+    NotLittleEndian,
+    WrongMachine(u16),
+    // only position independent executables can be loaded anywhere in physical memory
+    NotPositionIndependent(u16),
+    NoLoadableSegments,
+    InvalidSegment(usize),
+    UnsupportedDynamicTag(i64),
+    UnsupportedRelocation(u32),
+    RelocationOutOfBounds(u64),
+    EntryOutOfBounds(u64),
+    AllocatePages { error: efi::Error, pages: usize },
+}
+
+// only for the plain structs above, where every bit pattern is valid
+fn read<T: Copy>(bytes: &[u8], offset: usize) -> Result<T, ElfError> {
+    let bytes = bytes
+        .get(offset..)
+        .and_then(|bytes| bytes.get(..size_of::<T>()))
+        .ok_or(ElfError::Truncated)?;
+    Ok(unsafe { bytes.as_ptr().cast::<T>().read_unaligned() })
+}
+
+pub struct LoadedElf {
+    // the physical pages the image was copied to
+    pub image: Range<usize>,
+    pub entry: usize,
+}
+
+// copies the image into freshly allocated pages of `memory_type` and applies its relocations
+pub unsafe fn load_elf(
+    system_table: efi::SystemTable,
+    bytes: &[u8],
+    memory_type: efi::MemoryType,
+) -> Result<LoadedElf, ElfError> {
+    let header = read::<Header>(bytes, 0)?;
+    if header.ident[..4] != ELF_MAGIC {
+        return Err(ElfError::NotElf);
+    }
+    if header.ident[4] != ELF_CLASS_64 {
+        return Err(ElfError::Not64Bit);
+    }
+    if header.ident[5] != ELF_DATA_LITTLE_ENDIAN {
+        return Err(ElfError::NotLittleEndian);
+    }
+    if header.machine != ELF_MACHINE_X86_64 {
+        return Err(ElfError::WrongMachine(header.machine));
+    }
+    if header.kind != ELF_TYPE_DYNAMIC {
+        return Err(ElfError::NotPositionIndependent(header.kind));
+    }
+
+    let program_headers = (0..header.program_header_count as usize).map(|index| {
+        read::<ProgramHeader>(
+            bytes,
+            header.program_header_offset as usize + index * header.program_header_size as usize,
+        )
+    });
+
+    let mut start = u64::MAX;
+    let mut end = 0;
+    for (index, program_header) in program_headers.clone().enumerate() {
+        let program_header = program_header?;
+        if program_header.kind != PROGRAM_TYPE_LOAD {
+            continue;
+        }
+        if program_header.file_size > program_header.memory_size
+            || program_header
+                .offset
+                .checked_add(program_header.file_size)
+                .is_none_or(|end| end > bytes.len() as u64)
+        {
+            return Err(ElfError::InvalidSegment(index));
+        }
+        start = start.min(program_header.virtual_address);
+        end = end.max(
+            program_header
+                .virtual_address
+                .checked_add(program_header.memory_size)
+                .ok_or(ElfError::InvalidSegment(index))?,
+        );
+    }
+    if start >= end {
+        return Err(ElfError::NoLoadableSegments);
+    }
+
+    let start = start as usize / PAGE_SIZE * PAGE_SIZE;
+    let end = (end as usize).next_multiple_of(PAGE_SIZE);
+    let pages = (end - start) / PAGE_SIZE;
+    let image =
+        unsafe { system_table.allocate_pages(efi::AllocateType::AnyPages, memory_type, pages) }
+            .map_err(|error| ElfError::AllocatePages { error, pages })?
+            .addr();
+
+    // the lowest segment ends up at the start of the allocation
+    let base = image.wrapping_sub(start);
+    unsafe {
+        core::ptr::write_bytes(
+            core::ptr::with_exposed_provenance_mut::<u8>(image),
+            0,
+            end - start,
+        )
+    };
+
+    let mut dynamic = None;
+    for program_header in program_headers {
+        let program_header = program_header?;
+        match program_header.kind {
+            PROGRAM_TYPE_LOAD => unsafe {
+                core::ptr::copy_nonoverlapping(
+                    bytes.as_ptr().add(program_header.offset as usize),
+                    core::ptr::with_exposed_provenance_mut(
+                        base.wrapping_add(program_header.virtual_address as usize),
+                    ),
+                    program_header.file_size as usize,
+                );
+            },
+            PROGRAM_TYPE_DYNAMIC => dynamic = Some(program_header),
+            _ => {}
+        }
+    }
+
+    let image = image..image + (end - start);
+    let in_image = |address: u64, size: usize| {
+        let address = base.wrapping_add(address as usize);
+        image.contains(&address) && address + size <= image.end
+    };
+
+    if let Some(dynamic) = dynamic {
+        let mut rela = 0;
+        let mut rela_size = 0;
+        let mut rela_entry_size = size_of::<Rela>() as u64;
+        for index in 0..dynamic.file_size as usize / size_of::<Dynamic>() {
+            let entry = read::<Dynamic>(
+                bytes,
+                dynamic.offset as usize + index * size_of::<Dynamic>(),
+            )?;
+            match entry.tag {
+                DYNAMIC_TAG_NULL => break,
+                DYNAMIC_TAG_RELA => rela = entry.value,
+                DYNAMIC_TAG_RELA_SIZE => rela_size = entry.value,
+                DYNAMIC_TAG_RELA_ENTRY_SIZE => rela_entry_size = entry.value,
+                DYNAMIC_TAG_REL | DYNAMIC_TAG_RELR => {
+                    return Err(ElfError::UnsupportedDynamicTag(entry.tag));
+                }
+                _ => {}
+            }
+        }
+
+        if rela_size != 0 && !in_image(rela, rela_size as usize) {
+            return Err(ElfError::RelocationOutOfBounds(rela));
+        }
+        for offset in (0..rela_size).step_by(rela_entry_size.max(1) as usize) {
+            let relocation = unsafe {
+                core::ptr::with_exposed_provenance::<Rela>(
+                    base.wrapping_add((rela + offset) as usize),
+                )
+                .read_unaligned()
+            };
+            match relocation.info as u32 {
+                RELOCATION_NONE => {}
+                RELOCATION_RELATIVE => {
+                    if !in_image(relocation.offset, size_of::<u64>()) {
+                        return Err(ElfError::RelocationOutOfBounds(relocation.offset));
+                    }
+                    unsafe {
+                        core::ptr::with_exposed_provenance_mut::<u64>(
+                            base.wrapping_add(relocation.offset as usize),
+                        )
+                        .write_unaligned(base.wrapping_add_signed(relocation.addend as isize) as u64)
+                    };
+                }
+                kind => return Err(ElfError::UnsupportedRelocation(kind)),
+            }
+        }
+    }
+
+    if !in_image(header.entry, 1) {
+        return Err(ElfError::EntryOutOfBounds(header.entry));
+    }
+
+    Ok(LoadedElf {
+        entry: base.wrapping_add(header.entry as usize),
+        image,
+    })
+}
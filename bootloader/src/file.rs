@@ -0,0 +1,78 @@
+use core::ops::{Deref, DerefMut};
+
+const PAGE_SIZE: usize = 4096;
+
+// the pages are kept as well as the bytes, since a short read leaves fewer bytes than were allocated for
+pub struct FileBuffer {
+    bytes: &'static mut [u8],
+    pages: usize,
+}
+
+impl Deref for FileBuffer {
+    type Target = [u8];
+
+    fn deref(&self) -> &Self::Target {
+        self.bytes
+    }
+}
+
+impl DerefMut for FileBuffer {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.bytes
+    }
+}
+
+// reads a whole file from the volume on `device_handle` into loader data pages, which should be given back with `free_file`
+pub unsafe fn read_file(
+    system_table: efi::SystemTable,
+    device_handle: efi::Handle,
+    path: &[u16],
+) -> Result<FileBuffer, efi::Error> {
+    let root = unsafe { system_table.simple_file_system(device_handle)? }.open_volume()?;
+    let file = root.open(path, efi::FileMode::READ, efi::FileAttribute::NONE);
+    _ = root.close();
+    let file = file?;
+
+    let result = (|| {
+        let mut info = [0; efi::FileInfo::BUFFER_SIZE];
+        let info = file.info(&mut info)?;
+        if info.is_directory() {
+            return Err(efi::Error::INVALID_PARAMETER);
+        }
+
+        let size = info.file_size() as usize;
+        let pages = size.div_ceil(PAGE_SIZE).max(1);
+        let buffer = unsafe {
+            system_table.allocate_pages(
+                efi::AllocateType::AnyPages,
+                efi::MemoryType::LoaderData,
+                pages,
+            )?
+        };
+        let mut buffer = FileBuffer {
+            bytes: unsafe { core::slice::from_raw_parts_mut(buffer.cast::<u8>(), size) },
+            pages,
+        };
+
+        let mut read = 0;
+        while read < size {
+            match file.read(&mut buffer[read..]) {
+                Ok(0) => break,
+                Ok(count) => read += count,
+                Err(error) => {
+                    unsafe { free_file(system_table, buffer) };
+                    return Err(error);
+                }
+            }
+        }
+        buffer.bytes = &mut core::mem::take(&mut buffer.bytes)[..read];
+        Ok(buffer)
+    })();
+
+    _ = file.close();
+    result
+}
+
+pub unsafe fn free_file(system_table: efi::SystemTable, file: FileBuffer) {
+    _ = unsafe { system_table.free_pages(file.bytes.as_mut_ptr().cast(), file.pages) };
+}
@@ -1,267 +1,191 @@
-use crate::{
-    efi,
-    screen::{FramebufferColorPixels, Screen},
-};
-use core::{arch::asm, cell::SyncUnsafeCell};
-use utf16_literal::utf16;
-
-#[derive(Debug, Clone, Copy)]
-#[repr(C)]
-pub struct Color {
-    pub r: u8,
-    pub g: u8,
-    pub b: u8,
+use boot_info::{FramebufferInfo, PixelFormat};
+use core::{fmt::Write, str::FromStr};
+
+// how to pick the graphics mode, set with `resolution=` on the command line
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ModePolicy {
+    // whatever the firmware already set up
+    Current,
+    // the mode with the most pixels
+    Largest,
+    // `WxH`, the largest mode that fits inside it if there isnt an exact match
+    Resolution { width: usize, height: usize },
+    // `W:H`, the largest mode with that aspect ratio
+    AspectRatio { width: usize, height: usize },
 }
 
-impl Color {
-    pub const fn add(self, other: Self) -> Self {
-        Self {
-            r: self.r.saturating_add(other.r),
-            g: self.g.saturating_add(other.g),
-            b: self.b.saturating_add(other.b),
-        }
-    }
+impl FromStr for ModePolicy {
+    type Err = ();
 
-    pub const fn lerp(self, other: Self, t: u8) -> Self {
-        self.scale(u8::MAX - t).add(other.scale(t))
-    }
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let pair = |separator| {
+            let (width, height) = s.split_once(separator)?;
+            let width = width.trim().parse::<usize>().ok()?;
+            let height = height.trim().parse::<usize>().ok()?;
+            (width != 0 && height != 0).then_some((width, height))
+        };
 
-    pub const fn multiply(self, other: Self) -> Self {
-        Self {
-            r: ((self.r as u16 * other.r as u16) / u8::MAX as u16) as u8,
-            g: ((self.g as u16 * other.g as u16) / u8::MAX as u16) as u8,
-            b: ((self.b as u16 * other.b as u16) / u8::MAX as u16) as u8,
+        match s {
+            "current" => Ok(Self::Current),
+            "max" | "largest" => Ok(Self::Largest),
+            _ => {
+                if let Some((width, height)) = pair('x') {
+                    Ok(Self::Resolution { width, height })
+                } else if let Some((width, height)) = pair(':') {
+                    Ok(Self::AspectRatio { width, height })
+                } else {
+                    Err(())
+                }
+            }
         }
     }
-
-    pub const fn scale(self, brightness: u8) -> Self {
-        self.multiply(Color {
-            r: brightness,
-            g: brightness,
-            b: brightness,
-        })
-    }
-}
-
-enum FrameBufferFormat {
-    Rgb,
-    Bgr,
 }
 
-pub struct Framebuffer {
-    format: FrameBufferFormat,
-    pixels_base: *mut FramebufferColor,
-    pixels_width: usize,
-    pixels_height: usize,
-    pixels_per_scanline: usize,
+#[derive(Debug, Clone, Copy)]
+pub struct Mode {
+    pub number: u32,
+    pub width: usize,
+    pub height: usize,
+    pub pixels_per_scanline: usize,
+    pub format: PixelFormat,
 }
 
-unsafe impl Send for Framebuffer {}
-unsafe impl Sync for Framebuffer {}
-
-impl Framebuffer {
-    pub fn base(&self) -> usize {
-        self.pixels_base.addr()
-    }
-
-    pub fn size(&self) -> usize {
-        self.pixels_height * self.pixels_per_scanline
-    }
-
-    pub fn width(&self) -> usize {
-        self.pixels_width
-    }
-
-    pub fn height(&self) -> usize {
-        self.pixels_height
-    }
-
-    unsafe fn set_pixel_raw(&self, x: usize, y: usize, color: FramebufferColor) {
-        let pixel = unsafe { self.pixels_base.add(x + y * self.pixels_per_scanline) };
-        unsafe { pixel.write(color) };
-    }
-
-    unsafe fn get_pixel_raw(&self, x: usize, y: usize) -> FramebufferColor {
-        let pixel = unsafe { self.pixels_base.add(x + y * self.pixels_per_scanline) };
-        unsafe { pixel.read() }
-    }
-
-    pub fn set_pixel(&self, x: usize, y: usize, color: FramebufferColor) {
-        if x < self.pixels_width && y < self.pixels_height {
-            unsafe { self.set_pixel_raw(x, y, color) };
-            unsafe { asm!("/* {0} */", in(reg) self.pixels_base, options(nostack)) };
-        }
-    }
-
-    pub fn fill(
-        &self,
-        left: usize,
-        top: usize,
-        width: usize,
-        height: usize,
-        color: FramebufferColor,
-    ) {
-        let top = top.min(self.pixels_height);
-        let bottom = top.saturating_add(height).min(self.pixels_height);
-        let left = left.min(self.pixels_width);
-        let right = left.saturating_add(width).min(self.pixels_width);
-        for y in top..bottom {
-            for x in left..right {
-                unsafe { self.set_pixel_raw(x, y, color) };
+impl Mode {
+    // `None` for modes we cant draw to
+    fn new(number: u32, info: &efi::GOPModeInformation) -> Option<Self> {
+        let format = match info.pixel_format {
+            efi::GraphicsPixelFormat::RedGreenBlueReserved8BitPerColor => PixelFormat::Rgb,
+            efi::GraphicsPixelFormat::BlueGreenRedReserved8BitPerColor => PixelFormat::Bgr,
+            efi::GraphicsPixelFormat::BitMask => {
+                let efi::PixelBitmask {
+                    red_mask,
+                    green_mask,
+                    blue_mask,
+                    reserved_mask,
+                } = info.pixel_information;
+                let overlapping =
+                    red_mask & green_mask | green_mask & blue_mask | blue_mask & red_mask;
+                if red_mask | green_mask | blue_mask == 0 || overlapping != 0 {
+                    return None;
+                }
+                PixelFormat::Bitmask {
+                    red: red_mask,
+                    green: green_mask,
+                    blue: blue_mask,
+                    reserved: reserved_mask,
+                }
             }
-        }
-        unsafe { asm!("/* {0} */", in(reg) self.pixels_base, options(nostack)) };
+            efi::GraphicsPixelFormat::BltOnly => return None,
+        };
+        Some(Self {
+            number,
+            width: info.horizontal_resolution as _,
+            height: info.vertical_resolution as _,
+            pixels_per_scanline: info.pixels_per_scan_line as _,
+            format,
+        })
     }
 
-    pub fn copy_fullscreen(&self, screen: &FramebufferColorPixels) {
-        assert_eq!(screen.width(), self.width());
-        assert_eq!(screen.height(), self.height());
-
-        for y in 0..self.pixels_height {
-            unsafe {
-                core::ptr::copy_nonoverlapping(
-                    screen.pixels().add(y * self.pixels_width),
-                    self.pixels_base.add(y * self.pixels_per_scanline),
-                    self.pixels_width,
-                );
-            }
-        }
-
-        unsafe { asm!("/* {0} */", in(reg) self.pixels_base, options(nostack)) };
+    fn area(&self) -> usize {
+        self.width * self.height
     }
 }
 
-impl Screen for &Framebuffer {
-    fn width(&self) -> usize {
-        self.pixels_width
-    }
-
-    fn height(&self) -> usize {
-        self.pixels_height
-    }
-
-    unsafe fn set_pixel_unchecked(&mut self, x: usize, y: usize, color: Color) {
-        unsafe { self.set_pixel_raw(x, y, FramebufferColor::new(color)) };
-        unsafe { asm!("/* {0} */", in(reg) self.pixels_base, options(nostack)) };
-    }
-
-    unsafe fn get_pixel_unchecked(&self, x: usize, y: usize) -> Color {
-        unsafe { asm!("/* {0} */", in(reg) self.pixels_base, options(nostack)) };
-        unsafe { self.get_pixel_raw(x, y).color() }
-    }
-
-    fn set_pixel(&mut self, x: usize, y: usize, color: Color) {
-        (*self).set_pixel(x, y, FramebufferColor::new(color))
-    }
-
-    fn get_pixel(&self, x: usize, y: usize) -> Option<Color> {
-        if x < self.width() && y < self.height() {
-            Some(unsafe { self.get_pixel_unchecked(x, y) })
-        } else {
-            None
-        }
-    }
-
-    fn fill(&mut self, left: usize, top: usize, width: usize, height: usize, color: Color) {
-        (*self).fill(left, top, width, height, FramebufferColor::new(color))
-    }
-
-    fn copy(&mut self, screen: &dyn Screen, left: usize, top: usize) {
-        let width = screen.width();
-        let height = screen.height();
-
-        let top = top.min(self.pixels_height);
-        let bottom = top.saturating_add(height).min(self.pixels_height);
-        let left = left.min(self.pixels_width);
-        let right = left.saturating_add(width).min(self.pixels_width);
-
-        for y in top..bottom {
-            for x in left..right {
-                unsafe {
-                    self.set_pixel_raw(
-                        x,
-                        y,
-                        FramebufferColor::new(screen.get_pixel_unchecked(x - left, y - top)),
-                    );
-                }
-            }
-        }
-
-        unsafe { asm!("/* {0} */", in(reg) self.pixels_base, options(nostack)) };
-    }
+// every mode that can be drawn to
+pub fn usable_modes(system_table: efi::SystemTable, gop: efi::GOP) -> impl Iterator<Item = Mode> {
+    gop.modes(system_table)
+        .filter_map(|(number, info)| Mode::new(number, &info))
 }
 
-#[derive(Clone, Copy)]
-#[repr(C, align(4))]
-pub struct FramebufferColor([u8; 3]);
-
-impl FramebufferColor {
-    pub fn new(color: Color) -> Self {
-        FramebufferColor(match framebuffer().format {
-            FrameBufferFormat::Rgb => [color.r, color.g, color.b],
-            FrameBufferFormat::Bgr => [color.b, color.g, color.r],
-        })
-    }
-
-    pub fn color(self) -> Color {
-        let [x, y, z] = self.0;
-        let (r, g, b) = match framebuffer().format {
-            FrameBufferFormat::Rgb => (x, y, z),
-            FrameBufferFormat::Bgr => (z, y, x),
-        };
-        Color { r, g, b }
-    }
+// the mode the firmware has set right now, if it can be drawn to
+pub fn current_mode(system_table: efi::SystemTable, gop: efi::GOP) -> Option<Mode> {
+    let current = gop.current_mode()?;
+    usable_modes(system_table, gop).find(|mode| mode.number == current)
 }
 
-static FRAMEBUFFER: SyncUnsafeCell<Framebuffer> = SyncUnsafeCell::new(Framebuffer {
-    format: FrameBufferFormat::Rgb,
-    pixels_base: core::ptr::null_mut(),
-    pixels_width: 0,
-    pixels_height: 0,
-    pixels_per_scanline: 0,
-});
+pub fn select_mode(
+    system_table: efi::SystemTable,
+    gop: efi::GOP,
+    policy: ModePolicy,
+) -> Option<Mode> {
+    let current = current_mode(system_table, gop);
+    let largest = || usable_modes(system_table, gop).max_by_key(Mode::area);
+
+    let selected = match policy {
+        ModePolicy::Current => current,
+        ModePolicy::Largest => largest(),
+        ModePolicy::Resolution { width, height } => usable_modes(system_table, gop)
+            .find(|mode| mode.width == width && mode.height == height)
+            .or_else(|| {
+                usable_modes(system_table, gop)
+                    .filter(|mode| mode.width <= width && mode.height <= height)
+                    .max_by_key(Mode::area)
+            }),
+        ModePolicy::AspectRatio { width, height } => usable_modes(system_table, gop)
+            .filter(|mode| mode.width * height == mode.height * width)
+            .max_by_key(Mode::area),
+    };
 
-pub fn framebuffer() -> &'static Framebuffer {
-    unsafe { &*FRAMEBUFFER.get() }
+    selected.or(current).or_else(largest)
 }
 
-pub unsafe fn init_framebuffer(system_table: efi::SystemTable) -> efi::Status {
+pub unsafe fn init_framebuffer(
+    system_table: efi::SystemTable,
+    policy: ModePolicy,
+) -> Result<FramebufferInfo, efi::Error> {
     let gop = unsafe { system_table.locate_gop()? };
-    let (_, info) =
-        unsafe { gop.query_mode(gop.mode().map(|mode| (*mode.as_ptr()).mode).unwrap_or(0))? };
 
-    let format = match info.pixel_format {
-        efi::GraphicsPixelFormat::RedGreenBlueReserved8BitPerColor => FrameBufferFormat::Rgb,
-        efi::GraphicsPixelFormat::BlueGreenRedReserved8BitPerColor => FrameBufferFormat::Bgr,
-        efi::GraphicsPixelFormat::BitMask => unsafe {
-            system_table
-                .con_out_print(utf16!("bitmask pixel format is not supported\r\n\0").as_ptr())?;
-            for _ in 0..999999999 {
-                asm!("nop");
-            }
-            return Err(efi::Error::UNSUPPORTED);
-        },
-        efi::GraphicsPixelFormat::BltOnly => unsafe {
-            system_table
-                .con_out_print(utf16!("blt pixel format is not supported\r\n\0").as_ptr())?;
-            for _ in 0..999999999 {
-                asm!("nop");
-            }
-            return Err(efi::Error::UNSUPPORTED);
-        },
+    let Some(mut mode) = select_mode(system_table, gop, policy) else {
+        _ = writeln!(
+            system_table.con_out(),
+            "None of the {} graphics modes have a supported pixel format",
+            gop.max_mode()
+        );
+        return Err(efi::Error::UNSUPPORTED);
     };
 
-    unsafe { gop.set_mode(gop.mode().map(|mode| (*mode.as_ptr()).mode).unwrap_or(0))? };
-    let mode = unsafe { gop.mode().unwrap_unchecked().read() };
+    if gop.current_mode() != Some(mode.number)
+        && let Err(error) = gop.set_mode(mode.number)
+    {
+        // keep going with whatever is still set, as long as it is usable
+        // anything else would describe a framebuffer that doesn't match the one that is live
+        _ = writeln!(
+            system_table.con_out(),
+            "Failed to set graphics mode {} ({}x{}): {error:?}",
+            mode.number,
+            mode.width,
+            mode.height
+        );
+        mode = current_mode(system_table, gop).ok_or(error)?;
+    }
+
+    let gop_mode = unsafe { gop.mode().ok_or(efi::Error::DEVICE_ERROR)?.read() };
+    Ok(FramebufferInfo {
+        base: gop_mode.frame_buffer_base.addr(),
+        size: gop_mode.frame_buffer_size,
+        width: mode.width,
+        height: mode.height,
+        pixels_per_scanline: mode.pixels_per_scanline,
+        format: mode.format,
+    })
+}
 
-    unsafe {
-        *FRAMEBUFFER.get() = Framebuffer {
-            format,
-            pixels_base: mode.frame_buffer_base.cast(),
-            pixels_width: info.horizontal_resolution as _,
-            pixels_height: info.vertical_resolution as _,
-            pixels_per_scanline: info.pixels_per_scan_line as _,
-        };
+// draws straight to the framebuffer, for when something goes wrong after boot services are gone
+pub fn fill(framebuffer: &FramebufferInfo, r: u8, g: u8, b: u8) {
+    let channel = |value: u8, mask: u32| {
+        let shift = mask.trailing_zeros() % 32;
+        ((value as u64 * (mask >> shift) as u64 / u8::MAX as u64) as u32) << shift
+    };
+    let [red, green, blue] = framebuffer.format.masks();
+    let pixel = (channel(r, red) | channel(g, green) | channel(b, blue)).to_le_bytes();
+    let bytes_per_pixel = framebuffer.format.bytes_per_pixel();
+    let base = core::ptr::with_exposed_provenance_mut::<u8>(framebuffer.base);
+    for y in 0..framebuffer.height {
+        for x in 0..framebuffer.width {
+            let address = (x + y * framebuffer.pixels_per_scanline) * bytes_per_pixel;
+            for (index, byte) in pixel[..bytes_per_pixel].iter().enumerate() {
+                unsafe { base.add(address + index).write_volatile(*byte) };
+            }
+        }
     }
-    Ok(())
 }
@@ -1,143 +0,0 @@
-use crate::{
-    cpuid::{cpuid, is_cpuid_supported},
-    drivers::{
-        pic::{PIC1_DATA, PIC2_DATA, remap_pic},
-        ps2_keyboard::{KEYBOARD_STATE, Key, keyboard_handler, setup_keyboard},
-        ps2_mouse::{MOUSE_STATE, mouse_handler, setup_mouse},
-    },
-    framebuffer::{Color, FramebufferColor, framebuffer},
-    gdt::setup_gdt,
-    idt::{InterruptType, disable_interrupts, enable_interrupts, setup_idt, with_idt_entry},
-    screen::{FramebufferColorPixels, Screen},
-    text_writer::TextWriter,
-    utils::{io_wait, outb},
-};
-use alloc::vec;
-use core::{fmt::Write, mem::MaybeUninit};
-use font::SPACE_MONO;
-
-pub unsafe extern "win64" fn kernel_main() -> ! {
-    unsafe { disable_interrupts() };
-
-    let framebuffer = framebuffer();
-    let mut pixels = FramebufferColorPixels::new(
-        FramebufferColor::new(Color { r: 0, g: 0, b: 0 }),
-        framebuffer.width(),
-        framebuffer.height(),
-    );
-
-    unsafe { setup_gdt() };
-    unsafe { setup_idt() };
-
-    unsafe { remap_pic(0x20, 0x28) };
-
-    unsafe {
-        with_idt_entry(0x21, |entry| {
-            entry.set_handler(keyboard_handler, InterruptType::Interrupt);
-        });
-    }
-    unsafe { setup_keyboard() };
-
-    unsafe {
-        with_idt_entry(0x2C, |entry| {
-            entry.set_handler(mouse_handler, InterruptType::Interrupt);
-        });
-    }
-    unsafe { setup_mouse() };
-
-    unsafe { outb::<PIC1_DATA>(0b11111001) };
-    io_wait();
-    unsafe { outb::<PIC2_DATA>(0b11101111) };
-    io_wait();
-
-    unsafe { enable_interrupts() };
-
-    assert!(is_cpuid_supported());
-
-    let mut cpu_name = [0u8; 12];
-    let (max_cpuid, cpu_name) = {
-        let result = unsafe { cpuid(0, MaybeUninit::uninit()) };
-        cpu_name[0..4].copy_from_slice(&result.ebx.to_ne_bytes());
-        cpu_name[4..8].copy_from_slice(&result.edx.to_ne_bytes());
-        cpu_name[8..12].copy_from_slice(&result.ecx.to_ne_bytes());
-        (
-            result.eax,
-            core::str::from_utf8(&cpu_name).expect("the cpu name should be valid utf8"),
-        )
-    };
-
-    let max_extended_cpuid = unsafe { cpuid(0x80000000, MaybeUninit::uninit()).eax };
-
-    let mut changed = true;
-    let mut events = vec![];
-
-    let mut mouse_x = 0usize;
-    let mut mouse_y = 0usize;
-    loop {
-        KEYBOARD_STATE.with(|keyboard| {
-            while let Some(event) = keyboard.next_event() {
-                changed = true;
-                if matches!(event.key, Key::Backspace) {
-                    events.clear();
-                }
-                events.push(event);
-            }
-        });
-
-        MOUSE_STATE.with(|mouse| {
-            while let Some(event) = mouse.next_event() {
-                changed = true;
-                mouse_x = mouse_x
-                    .saturating_add_signed(event.x_offset as isize)
-                    .min(framebuffer.width() - 1);
-                mouse_y = mouse_y
-                    .saturating_add_signed(-(event.y_offset as isize))
-                    .min(framebuffer.height() - 1);
-            }
-        });
-
-        if changed {
-            let background = Color {
-                r: 50,
-                g: 50,
-                b: 50,
-            };
-            pixels.fill(0, 0, pixels.width(), pixels.height(), background);
-
-            {
-                let mut writer = TextWriter {
-                    x: &mut 0,
-                    y: &mut 0,
-                    left_margin: 0,
-                    text_color: Color {
-                        r: 255,
-                        g: 255,
-                        b: 255,
-                    },
-                    background,
-                    font: &SPACE_MONO,
-                    screen: &mut pixels,
-                };
-                writeln!(writer, "Max CPUID: {:#X}", max_cpuid).unwrap();
-                writeln!(writer, "Max Extended CPUID: {:#X}", max_extended_cpuid).unwrap();
-                writeln!(writer, "Cpu Name: {:?}", cpu_name).unwrap();
-                for event in &events {
-                    writeln!(writer, "{event:?}").unwrap();
-                }
-            }
-
-            pixels.fill(
-                mouse_x,
-                mouse_y,
-                10,
-                10,
-                Color {
-                    r: 255,
-                    g: 255,
-                    b: 255,
-                },
-            );
-            framebuffer.copy_fullscreen(&pixels);
-        }
-    }
-}
@@ -1,127 +1,293 @@
 #![no_std]
 #![no_main]
-#![feature(
-    sync_unsafe_cell,
-    format_args_nl,
-    abi_x86_interrupt,
-    const_precise_live_drops
-)]
+#![feature(sync_unsafe_cell)]
 
 use crate::{
-    framebuffer::{Color, FramebufferColor, framebuffer, init_framebuffer},
-    idt::disable_interrupts,
-    kernel::kernel_main,
-    page_allocator::{PAGE_ALLOCATOR, init_page_allocator},
-    utils::{error_screen, hlt},
+    elf::load_elf,
+    file::{free_file, read_file},
+    framebuffer::{ModePolicy, fill, init_framebuffer},
 };
-use core::panic::PanicInfo;
-use core::{alloc::Layout, arch::asm, fmt::Write};
+use boot_info::{
+    BootInfo, FramebufferInfo, KERNEL_CODE, KERNEL_DATA, KernelEntry,
+    kernel_args::{ByteSize, KernelArgs},
+};
+use core::{
+    arch::asm,
+    cell::SyncUnsafeCell,
+    fmt::{Arguments, Write},
+    panic::PanicInfo,
+    str::FromStr,
+};
+use utf16_literal::utf16;
 
-pub mod cpuid;
-pub mod drivers;
-pub mod efi;
+pub mod elf;
+pub mod file;
 pub mod framebuffer;
-pub mod gdt;
-pub mod idt;
-pub mod interrupt_safe_mutex;
-pub mod kernel;
-pub mod page_allocator;
-pub mod rust_global_allocators;
-pub mod screen;
-pub mod text_writer;
-pub mod utils;
-
-extern crate alloc;
+
+const PAGE_SIZE: usize = 4096;
+const KERNEL_PATH: &[u16] = utf16!("\\efi\\boot\\kernel.elf\0");
+const CONFIG_PATH: &[u16] = utf16!("\\efi\\boot\\cmdline.txt\0");
+const DEFAULT_KERNEL_STACK_SIZE: usize = 4 * 1024 * 1024;
+const MIN_KERNEL_STACK_SIZE: usize = 64 * 1024;
+
+// only set while boot services are still around, the panic handler uses it to print to the console
+static SYSTEM_TABLE: SyncUnsafeCell<Option<efi::SystemTable>> = SyncUnsafeCell::new(None);
+static FRAMEBUFFER: SyncUnsafeCell<Option<FramebufferInfo>> = SyncUnsafeCell::new(None);
 
 #[unsafe(no_mangle)]
 unsafe extern "efiapi" fn efi_main(
     image_handle: efi::Handle,
     system_table: efi::SystemTable,
 ) -> efi::Status {
-    unsafe { init_framebuffer(system_table)? };
+    unsafe { *SYSTEM_TABLE.get() = Some(system_table) };
+
+    let loaded_image = match unsafe { system_table.loaded_image(image_handle) } {
+        Ok(loaded_image) => loaded_image,
+        Err(error) => unsafe {
+            return boot_failed(
+                system_table,
+                error,
+                format_args!("Failed to get the loaded image: {error:?}"),
+            );
+        },
+    };
 
-    let framebuffer = framebuffer();
-    let width = framebuffer.width();
-    let height = framebuffer.height();
+    // arguments from the config file come first, so the ones the image was started with override them
+    let config = match unsafe { read_file(system_table, loaded_image.device_handle(), CONFIG_PATH) }
+    {
+        Ok(config) => Some(config),
+        Err(efi::Error::NOT_FOUND) => None,
+        Err(error) => {
+            _ = writeln!(
+                system_table.con_out(),
+                "Failed to read the config file: {error:?}"
+            );
+            None
+        }
+    };
+    let config_text = config.as_deref().map_or("", |config| {
+        core::str::from_utf8(config).unwrap_or_else(|error| {
+            _ = writeln!(system_table.con_out(), "Ignoring the config file: {error}");
+            ""
+        })
+    });
 
-    let background = Color {
-        r: 20,
-        g: 20,
-        b: 20,
+    // the command line is stored right after the boot info, in the same pages
+    let load_options = loaded_image.load_options();
+    let command_line_capacity = config_text.len() + 1 + load_options.len() / 2 * 3;
+    let boot_info_pages = (size_of::<BootInfo>() + command_line_capacity).div_ceil(PAGE_SIZE);
+    let boot_info = match unsafe {
+        system_table.allocate_pages(efi::AllocateType::AnyPages, KERNEL_DATA, boot_info_pages)
+    } {
+        Ok(boot_info) => boot_info.cast::<BootInfo>(),
+        Err(error) => unsafe {
+            return boot_failed(
+                system_table,
+                error,
+                format_args!("Failed to allocate the boot info: {error:?}"),
+            );
+        },
     };
-    framebuffer.fill(0, 0, width, height, FramebufferColor::new(background));
-
-    // get memory map
-    let mut memory_map_size = 0;
-    let mut memory_map = core::ptr::null_mut();
-    let mut map_key = 0;
-    let mut memory_descriptor_size = 0;
-    let mut memory_descritor_version = 0;
-    loop {
-        let old_size = memory_map_size;
-        match unsafe {
-            system_table.get_memory_map(
-                &mut memory_map_size,
-                memory_map,
-                &mut map_key,
-                &mut memory_descriptor_size,
-                &mut memory_descritor_version,
-            )
-        } {
-            Err(efi::Error::BUFFER_TOO_SMALL) => {
-                if !memory_map.is_null() {
-                    unsafe { system_table.free_pages(memory_map.cast(), old_size.div_ceil(4096))? };
+
+    let command_line = unsafe {
+        core::slice::from_raw_parts_mut(boot_info.add(1).cast::<u8>(), command_line_capacity)
+    };
+    command_line[..config_text.len()].copy_from_slice(config_text.as_bytes());
+    command_line[config_text.len()] = b' ';
+    let command_line_length = config_text.len()
+        + 1
+        + decode_load_options(load_options, &mut command_line[config_text.len() + 1..]);
+    if let Some(config) = config {
+        unsafe { free_file(system_table, config) };
+    }
+    let kernel_args = KernelArgs::new(
+        core::str::from_utf8(&command_line[..command_line_length])
+            .expect("the command line should have been decoded to utf8"),
+    );
+
+    let framebuffer = unsafe {
+        init_framebuffer(
+            system_table,
+            arg_or(system_table, kernel_args, "resolution", ModePolicy::Current),
+        )?
+    };
+    unsafe { *FRAMEBUFFER.get() = Some(framebuffer) };
+
+    let kernel_file =
+        match unsafe { read_file(system_table, loaded_image.device_handle(), KERNEL_PATH) } {
+            Ok(kernel_file) => kernel_file,
+            Err(error) => unsafe {
+                return boot_failed(
+                    system_table,
+                    error,
+                    format_args!("Failed to read the kernel from the boot partition: {error:?}"),
+                );
+            },
+        };
+
+    let kernel = match unsafe { load_elf(system_table, &kernel_file, KERNEL_CODE) } {
+        Ok(kernel) => kernel,
+        Err(error) => unsafe {
+            return boot_failed(
+                system_table,
+                efi::Error::LOAD_ERROR,
+                format_args!("Failed to load the kernel: {error:?}"),
+            );
+        },
+    };
+    unsafe { free_file(system_table, kernel_file) };
+
+    let ByteSize(stack_size) = arg_or(
+        system_table,
+        kernel_args,
+        "stack_size",
+        ByteSize(DEFAULT_KERNEL_STACK_SIZE),
+    );
+    let stack_size = stack_size
+        .max(MIN_KERNEL_STACK_SIZE)
+        .next_multiple_of(PAGE_SIZE);
+    let stack = match unsafe {
+        system_table.allocate_pages(
+            efi::AllocateType::AnyPages,
+            KERNEL_DATA,
+            stack_size / PAGE_SIZE,
+        )
+    } {
+        Ok(stack) => stack.addr(),
+        Err(error) => unsafe {
+            return boot_failed(
+                system_table,
+                error,
+                format_args!("Failed to allocate the kernel stack: {error:?}"),
+            );
+        },
+    };
+
+    let rsdp = unsafe {
+        system_table
+            .configuration_table(efi::Guid::ACPI_20_TABLE)
+            .or_else(|| system_table.configuration_table(efi::Guid::ACPI_TABLE))
+    };
+
+    // nothing can be allocated after this, or the memory map would be out of date
+    unsafe { *SYSTEM_TABLE.get() = None };
+    let mut memory_map =
+        match unsafe { efi::MemoryMap::exit_boot_services(system_table, image_handle) } {
+            Ok(memory_map) => memory_map,
+            // boot services might already be partially shut down, so the console cant be used here
+            Err(_) => {
+                fill(&framebuffer, 255, 0, 0);
+                loop {
+                    hlt();
                 }
-                memory_map = unsafe {
-                    system_table
-                        .allocate_pages(
-                            efi::AllocateType::AnyPages,
-                            efi::MemoryType::LoaderData,
-                            memory_map_size.div_ceil(4096),
-                        )?
-                        .cast()
-                };
-                continue;
             }
-            result => break result?,
-        }
-    }
-    assert!(memory_map_size.is_multiple_of(memory_descriptor_size));
+        };
 
-    // exit boot services
-    unsafe { system_table.exit_boot_services(image_handle, map_key)? };
+    unsafe { asm!("cli", options(nomem, nostack)) };
 
-    unsafe { disable_interrupts() };
+    memory_map.sort();
+    memory_map.merge();
 
-    unsafe { init_page_allocator(memory_map, memory_map_size, memory_descriptor_size) };
+    unsafe {
+        boot_info.write(BootInfo {
+            version: BootInfo::VERSION,
+            memory_map,
+            framebuffer,
+            rsdp: rsdp.map(|rsdp| rsdp.addr()),
+            command_line: command_line.as_ptr(),
+            command_line_length,
+        });
+    }
 
     unsafe {
-        let stack_size = 4 * 1024 * 1024;
-        let layout = Layout::from_size_align(stack_size, 16).unwrap();
-        let stack = PAGE_ALLOCATOR
-            .with(|alloc| alloc.allocate(layout))
-            .expect("allocating the stack should succeed");
-
-        let stack_start = stack + stack_size;
-        let _: unsafe extern "win64" fn() -> ! = kernel_main;
+        let entry = core::mem::transmute::<usize, KernelEntry>(kernel.entry);
         asm!(
-            "mov rsp, {stack_start}",
-            "call {kernel_main}",
-            stack_start = in(reg) stack_start,
-            kernel_main = sym kernel_main,
+            "mov rsp, {stack_top}",
+            "xor ebp, ebp",
+            "call {entry}",
+            stack_top = in(reg) stack + stack_size,
+            entry = in(reg) entry,
+            in("rdi") boot_info,
             options(noreturn)
         )
     }
 }
 
+// falls back to `default` if the argument is missing or invalid
+fn arg_or<T: FromStr>(
+    system_table: efi::SystemTable,
+    kernel_args: KernelArgs<'_>,
+    key: &str,
+    default: T,
+) -> T {
+    kernel_args
+        .parse(key)
+        .unwrap_or_else(|error| {
+            _ = writeln!(
+                system_table.con_out(),
+                "Ignoring invalid kernel argument: {error:?}"
+            );
+            None
+        })
+        .unwrap_or(default)
+}
+
+unsafe fn boot_failed(
+    system_table: efi::SystemTable,
+    error: efi::Error,
+    message: Arguments<'_>,
+) -> efi::Status {
+    _ = writeln!(system_table.con_out(), "{message}");
+    // leave the message up for a bit before the firmware moves on to the next boot option
+    _ = unsafe { system_table.stall(5_000_000) };
+    Err(error)
+}
+
+// the load options are a null terminated UCS-2 string, returns how many utf8 bytes were written
+fn decode_load_options(load_options: &[u8], command_line: &mut [u8]) -> usize {
+    let units = load_options
+        .chunks_exact(2)
+        .map(|unit| u16::from_le_bytes([unit[0], unit[1]]))
+        .take_while(|&unit| unit != 0);
+
+    let mut length = 0;
+    for c in char::decode_utf16(units) {
+        let c = c.unwrap_or(char::REPLACEMENT_CHARACTER);
+        length += c.encode_utf8(&mut command_line[length..]).len();
+    }
+
+    // the uefi shell passes the path to the image as the first word, boot entries usually dont
+    let decoded = &command_line[..length];
+    let start = decoded
+        .iter()
+        .position(|c| !c.is_ascii_whitespace())
+        .unwrap_or(length);
+    let end = decoded[start..]
+        .iter()
+        .position(u8::is_ascii_whitespace)
+        .map_or(length, |end| start + end);
+    if end - start >= 4 && decoded[end - 4..end].eq_ignore_ascii_case(b".efi") {
+        command_line.copy_within(end..length, 0);
+        length -= end;
+    }
+    length
+}
+
+fn hlt() {
+    unsafe { asm!("hlt", options(nomem, nostack)) };
+}
+
 #[panic_handler]
 fn panic(info: &PanicInfo<'_>) -> ! {
-    error_screen(|text_writer| {
+    if let Some(system_table) = unsafe { *SYSTEM_TABLE.get() } {
+        let mut con_out = system_table.con_out();
         if let Some(location) = info.location() {
-            _ = write!(text_writer, "{}: ", location);
+            _ = write!(con_out, "{}: ", location);
         }
-        _ = writeln!(text_writer, "{}", info.message());
-    });
+        _ = writeln!(con_out, "{}", info.message());
+    } else if let Some(framebuffer) = unsafe { &*FRAMEBUFFER.get() } {
+        fill(framebuffer, 255, 0, 0);
+    }
 
     loop {
         hlt();
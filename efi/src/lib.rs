@@ -0,0 +1,1398 @@
+#![no_std]
+
+use core::{
+    fmt::{Debug, Write},
+    num::NonZeroIsize,
+    ops::{BitOr, Range},
+    ptr::NonNull,
+};
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+#[repr(transparent)]
+pub struct Error(pub NonZeroIsize);
+
+impl Error {
+    pub const LOAD_ERROR: Self = Self(NonZeroIsize::new(isize::MIN | 1).unwrap());
+    pub const INVALID_PARAMETER: Self = Self(NonZeroIsize::new(isize::MIN | 2).unwrap());
+    pub const UNSUPPORTED: Self = Self(NonZeroIsize::new(isize::MIN | 3).unwrap());
+    pub const BAD_BUFFER_SIZE: Self = Self(NonZeroIsize::new(isize::MIN | 4).unwrap());
+    pub const BUFFER_TOO_SMALL: Self = Self(NonZeroIsize::new(isize::MIN | 5).unwrap());
+    pub const NOT_READY: Self = Self(NonZeroIsize::new(isize::MIN | 6).unwrap());
+    pub const DEVICE_ERROR: Self = Self(NonZeroIsize::new(isize::MIN | 7).unwrap());
+    pub const WRITE_PROTECTED: Self = Self(NonZeroIsize::new(isize::MIN | 8).unwrap());
+    pub const OUT_OF_RESOURCES: Self = Self(NonZeroIsize::new(isize::MIN | 9).unwrap());
+    pub const NOT_FOUND: Self = Self(NonZeroIsize::new(isize::MIN | 14).unwrap());
+    pub const ACCESS_DENIED: Self = Self(NonZeroIsize::new(isize::MIN | 15).unwrap());
+}
+
+impl Debug for Error {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match *self {
+            Self::LOAD_ERROR => write!(f, "EFI_LOAD_ERROR"),
+            Self::INVALID_PARAMETER => write!(f, "EFI_INVALID_PARAMETER"),
+            Self::UNSUPPORTED => write!(f, "EFI_UNSUPPORTED"),
+            Self::BAD_BUFFER_SIZE => write!(f, "EFI_BAD_BUFFER_SIZE"),
+            Self::BUFFER_TOO_SMALL => write!(f, "EFI_BUFFER_TOO_SMALL"),
+            Self::NOT_READY => write!(f, "EFI_NOT_READY"),
+            Self::DEVICE_ERROR => write!(f, "EFI_DEVICE_ERROR"),
+            Self::WRITE_PROTECTED => write!(f, "EFI_WRITE_PROTECTED"),
+            Self::OUT_OF_RESOURCES => write!(f, "EFI_OUT_OF_RESOURCES"),
+            Self::NOT_FOUND => write!(f, "EFI_NOT_FOUND"),
+            Self::ACCESS_DENIED => write!(f, "EFI_ACCESS_DENIED"),
+            Self(x) => write!(f, "EfiError({:#X})", x.get() & isize::MAX),
+        }
+    }
+}
+
+pub type Status = Result<(), Error>;
+
+const _: () = assert!(size_of::<Status>() == size_of::<Error>());
+
+#[derive(Debug, Clone, Copy)]
+#[repr(transparent)]
+pub struct Handle(*const ());
+
+#[derive(Debug, Clone, Copy)]
+#[repr(transparent)]
+pub struct SystemTable(*const SystemTableData);
+
+// boot services only ever run on the boot processor
+unsafe impl Send for SystemTable {}
+unsafe impl Sync for SystemTable {}
+
+impl SystemTable {
+    pub unsafe fn con_out_print(self, string: *const u16) -> Status {
+        unsafe {
+            let con_out = (*self.0).con_out;
+            ((*con_out).output_string)(con_out, string)
+        }
+    }
+
+    pub unsafe fn locate_protocol(self, guid: Guid) -> Result<*mut (), Error> {
+        let mut ptr = core::ptr::null_mut();
+        unsafe {
+            ((*(*self.0).boot_services).locate_protocol)(
+                &raw const guid,
+                core::ptr::null(),
+                &raw mut ptr,
+            )?;
+        }
+        Ok(ptr)
+    }
+
+    pub unsafe fn handle_protocol(self, handle: Handle, guid: Guid) -> Result<*mut (), Error> {
+        let mut ptr = core::ptr::null_mut();
+        unsafe {
+            ((*(*self.0).boot_services).handle_protocol)(handle, &raw const guid, &raw mut ptr)?;
+        }
+        Ok(ptr)
+    }
+
+    pub unsafe fn loaded_image(self, image_handle: Handle) -> Result<LoadedImage, Error> {
+        unsafe {
+            let protocol = self.handle_protocol(image_handle, Guid::LOADED_IMAGE_PROTOCOL)?;
+            Ok(LoadedImage(protocol.cast()))
+        }
+    }
+
+    pub unsafe fn simple_file_system(
+        self,
+        device_handle: Handle,
+    ) -> Result<SimpleFileSystem, Error> {
+        unsafe {
+            let protocol =
+                self.handle_protocol(device_handle, Guid::SIMPLE_FILE_SYSTEM_PROTOCOL)?;
+            Ok(SimpleFileSystem(protocol.cast()))
+        }
+    }
+
+    pub unsafe fn locate_gop(self) -> Result<GOP, Error> {
+        unsafe {
+            let protocol = self.locate_protocol(Guid::GRAPHICS_OUTPUT_PROTOCOL)?;
+            Ok(GOP(protocol.cast()))
+        }
+    }
+
+    pub unsafe fn allocate_pages(
+        self,
+        allocate_type: AllocateType,
+        memory_type: MemoryType,
+        pages: usize,
+    ) -> Result<*mut (), Error> {
+        let mut ptr = core::ptr::null_mut();
+        unsafe {
+            ((*(*self.0).boot_services).allocate_pages)(
+                allocate_type,
+                memory_type,
+                pages,
+                &mut ptr,
+            )?;
+        }
+        Ok(ptr)
+    }
+
+    pub unsafe fn free_pages(self, memory: *mut (), pages: usize) -> Status {
+        unsafe { ((*(*self.0).boot_services).free_pages)(memory, pages) }
+    }
+
+    pub unsafe fn free_pool(self, buffer: *mut ()) -> Status {
+        unsafe { ((*(*self.0).boot_services).free_pool)(buffer) }
+    }
+
+    pub unsafe fn get_memory_map(
+        self,
+        memory_map_size: &mut usize,
+        memory_map: *mut MemoryDescriptor,
+        map_key: &mut usize,
+        descriptor_size: &mut usize,
+        descriptor_version: &mut u32,
+    ) -> Status {
+        unsafe {
+            ((*(*self.0).boot_services).get_memory_map)(
+                memory_map_size,
+                memory_map,
+                map_key,
+                descriptor_size,
+                descriptor_version,
+            )
+        }
+    }
+
+    pub unsafe fn exit_boot_services(self, image_handle: Handle, map_key: usize) -> Status {
+        unsafe { ((*(*self.0).boot_services).exit_boot_services)(image_handle, map_key) }
+    }
+
+    pub unsafe fn stall(self, microseconds: usize) -> Status {
+        unsafe { ((*(*self.0).boot_services).stall)(microseconds) }
+    }
+
+    // the vendor table the firmware installed under this guid, if there is one
+    pub unsafe fn configuration_table(self, guid: Guid) -> Option<NonNull<()>> {
+        let tables = unsafe {
+            core::slice::from_raw_parts(
+                (*self.0).configuration_table,
+                (*self.0).number_of_table_entries,
+            )
+        };
+        tables
+            .iter()
+            .find(|table| table.vendor_guid == guid)
+            .and_then(|table| NonNull::new(table.vendor_table.cast_mut()))
+    }
+
+    pub fn con_out(self) -> ConOut {
+        ConOut(self)
+    }
+}
+
+// lets the firmware console be used with `write!`, only valid until boot services are exited
+#[derive(Debug, Clone, Copy)]
+pub struct ConOut(SystemTable);
+
+impl Write for ConOut {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        let mut buffer = [0u16; 128];
+        let mut length = 0;
+        let flush = |buffer: &mut [u16; 128], length: &mut usize| {
+            buffer[*length] = 0;
+            *length = 0;
+            unsafe { self.0.con_out_print(buffer.as_ptr()) }.map_err(|_| core::fmt::Error)
+        };
+
+        for c in s.chars() {
+            // room for a '\r', a surrogate pair and the null terminator
+            if length + 4 > buffer.len() {
+                flush(&mut buffer, &mut length)?;
+            }
+            if c == '\n' {
+                buffer[length] = '\r' as u16;
+                length += 1;
+            }
+            length += c.encode_utf16(&mut buffer[length..]).len();
+        }
+        flush(&mut buffer, &mut length)
+    }
+}
+
+#[repr(C)]
+struct TableHeader {
+    signature: u64,
+    revision: u32,
+    header_size: u32,
+    crc32: u32,
+    reserved: u32,
+}
+
+#[repr(C)]
+struct SystemTableData {
+    hdr: TableHeader,
+    firmware_vendor: *const u16,
+    firmware_revision: u32,
+    console_in_handle: Handle,
+    con_in: *const SimpleTextInputProtocol,
+    console_out_handle: Handle,
+    con_out: *const SimpleTextOutputProtocol,
+    standard_error_handle: Handle,
+    std_err: *const SimpleTextOutputProtocol,
+    runtime_services: *const RuntimeServices,
+    boot_services: *const BootServices,
+    number_of_table_entries: usize,
+    configuration_table: *const ConfigurationTable,
+}
+
+#[repr(C)]
+struct SimpleTextInputProtocol {}
+
+#[repr(C)]
+struct SimpleTextOutputProtocol {
+    reset: unsafe extern "efiapi" fn(),
+    output_string: unsafe extern "efiapi" fn(
+        this: *const SimpleTextOutputProtocol,
+        string: *const u16,
+    ) -> Status,
+    test_string: unsafe extern "efiapi" fn(),
+    query_mode: unsafe extern "efiapi" fn(),
+    set_mode: unsafe extern "efiapi" fn(),
+    set_attribute: unsafe extern "efiapi" fn(),
+    clear_screen: unsafe extern "efiapi" fn(),
+    set_cursor_position: unsafe extern "efiapi" fn(),
+    enable_cursor: unsafe extern "efiapi" fn(),
+    mode: *const SimpleTextOutputMode,
+}
+
+#[repr(C)]
+struct SimpleTextOutputMode {
+    max_mode: i32,
+    mode: i32,
+    attribute: i32,
+    cursor_column: i32,
+    cursor_row: i32,
+    cursor_visible: bool,
+}
+
+#[repr(C)]
+struct RuntimeServices {
+    hdr: TableHeader,
+    get_time: unsafe extern "efiapi" fn(),
+    set_time: unsafe extern "efiapi" fn(),
+    get_wakeup_time: unsafe extern "efiapi" fn(),
+    set_wakeup_time: unsafe extern "efiapi" fn(),
+    set_virtual_address_map: unsafe extern "efiapi" fn(),
+    convert_pointer: unsafe extern "efiapi" fn(),
+    get_variable: unsafe extern "efiapi" fn(),
+    get_next_variable_name: unsafe extern "efiapi" fn(),
+    set_variable: unsafe extern "efiapi" fn(),
+    get_next_high_monotonic_count: unsafe extern "efiapi" fn(),
+    reset_system: unsafe extern "efiapi" fn(),
+    update_capsule: unsafe extern "efiapi" fn(),
+    query_capsule_capabilities: unsafe extern "efiapi" fn(),
+    query_variable_info: unsafe extern "efiapi" fn(),
+}
+
+#[repr(C)]
+struct BootServices {
+    hdr: TableHeader,
+    raise_tpl: unsafe extern "efiapi" fn(),
+    restore_tpl: unsafe extern "efiapi" fn(),
+    allocate_pages: unsafe extern "efiapi" fn(
+        allocate_type: AllocateType,
+        memory_type: MemoryType,
+        pages: usize,
+        memory: *mut *mut (),
+    ) -> Status,
+    free_pages: unsafe extern "efiapi" fn(memory: *mut (), pages: usize) -> Status,
+    get_memory_map: unsafe extern "efiapi" fn(
+        memory_map_size: *mut usize,
+        memory_map: *mut MemoryDescriptor,
+        map_key: *mut usize,
+        descriptor_size: *mut usize,
+        descriptor_version: *mut u32,
+    ) -> Status,
+    allocate_pool: unsafe extern "efiapi" fn(),
+    free_pool: unsafe extern "efiapi" fn(buffer: *mut ()) -> Status,
+    create_event: unsafe extern "efiapi" fn(),
+    set_timer: unsafe extern "efiapi" fn(),
+    wait_for_event: unsafe extern "efiapi" fn(),
+    signal_event: unsafe extern "efiapi" fn(),
+    close_event: unsafe extern "efiapi" fn(),
+    check_event: unsafe extern "efiapi" fn(),
+    install_protocol_interface: unsafe extern "efiapi" fn(),
+    reinstall_protocol_interface: unsafe extern "efiapi" fn(),
+    uninstall_protocol_interface: unsafe extern "efiapi" fn(),
+    handle_protocol: unsafe extern "efiapi" fn(
+        handle: Handle,
+        protocol: *const Guid,
+        interface: *mut *mut (),
+    ) -> Status,
+    reserved: *const (),
+    register_protocol_notify: unsafe extern "efiapi" fn(),
+    locate_handle: unsafe extern "efiapi" fn(),
+    locate_device_path: unsafe extern "efiapi" fn(),
+    install_configuration_table: unsafe extern "efiapi" fn(),
+    load_image: unsafe extern "efiapi" fn(),
+    start_image: unsafe extern "efiapi" fn(),
+    exit: unsafe extern "efiapi" fn(),
+    unload_image: unsafe extern "efiapi" fn(),
+    exit_boot_services: unsafe extern "efiapi" fn(image_handle: Handle, map_key: usize) -> Status,
+    get_next_monotonic_count: unsafe extern "efiapi" fn(),
+    stall: unsafe extern "efiapi" fn(microseconds: usize) -> Status,
+    set_watchdog_timer: unsafe extern "efiapi" fn(),
+    connect_controller: unsafe extern "efiapi" fn(),
+    disconnect_controller: unsafe extern "efiapi" fn(),
+    open_protocol: unsafe extern "efiapi" fn(),
+    close_protocol: unsafe extern "efiapi" fn(),
+    open_protocol_information: unsafe extern "efiapi" fn(),
+    protocols_per_handle: unsafe extern "efiapi" fn(),
+    locate_handle_buffer: unsafe extern "efiapi" fn(),
+    locate_protocol: unsafe extern "efiapi" fn(
+        protocol: *const Guid,
+        registration: *const (),
+        interface: *mut *mut (),
+    ) -> Status,
+    install_multiple_protocol_interfaces: unsafe extern "efiapi" fn(),
+    uninstall_multiple_protocol_interfaces: unsafe extern "efiapi" fn(),
+    calculate_crc32: unsafe extern "efiapi" fn(),
+    copy_mem: unsafe extern "efiapi" fn(),
+    set_mem: unsafe extern "efiapi" fn(),
+    create_event_ex: unsafe extern "efiapi" fn(),
+}
+
+#[derive(Debug, Clone, Copy)]
+#[repr(C)]
+pub enum AllocateType {
+    AnyPages,
+    MaxAddress,
+    Address,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+#[repr(transparent)]
+pub struct MemoryType(pub u32);
+
+#[allow(non_upper_case_globals)]
+impl MemoryType {
+    pub const ReservedMemoryType: Self = Self(0);
+    pub const LoaderCode: Self = Self(1);
+    pub const LoaderData: Self = Self(2);
+    pub const BootServicesCode: Self = Self(3);
+    pub const BootServicesData: Self = Self(4);
+    pub const RuntimeServicesCode: Self = Self(5);
+    pub const RuntimeServicesData: Self = Self(6);
+    pub const ConventionalMemory: Self = Self(7);
+    pub const UnusableMemory: Self = Self(8);
+    pub const ACPIReclaimMemory: Self = Self(9);
+    pub const ACPIMemoryNVS: Self = Self(10);
+    pub const MemoryMappedIO: Self = Self(11);
+    pub const MemoryMappedIOPortSpace: Self = Self(12);
+    pub const PalCode: Self = Self(13);
+    pub const PersistentMemory: Self = Self(14);
+}
+
+impl Debug for MemoryType {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match *self {
+            Self::ReservedMemoryType => write!(f, "EfiReservedMemoryType"),
+            Self::LoaderCode => write!(f, "EfiLoaderCode"),
+            Self::LoaderData => write!(f, "EfiLoaderData"),
+            Self::BootServicesCode => write!(f, "EfiBootServicesCode"),
+            Self::BootServicesData => write!(f, "EfiBootServicesData"),
+            Self::RuntimeServicesCode => write!(f, "EfiRuntimeServicesCode"),
+            Self::RuntimeServicesData => write!(f, "EfiRuntimeServicesData"),
+            Self::ConventionalMemory => write!(f, "EfiConventionalMemory"),
+            Self::UnusableMemory => write!(f, "EfiUnusableMemory"),
+            Self::ACPIReclaimMemory => write!(f, "EfiACPIReclaimMemory"),
+            Self::ACPIMemoryNVS => write!(f, "EfiACPIMemoryNVS"),
+            Self::MemoryMappedIO => write!(f, "EfiMemoryMappedIO"),
+            Self::MemoryMappedIOPortSpace => write!(f, "EfiMemoryMappedIOPortSpace"),
+            Self::PalCode => write!(f, "EfiPalCode"),
+            Self::PersistentMemory => write!(f, "EfiPersistentMemory"),
+            Self(x) => write!(f, "EfiUnknownMemoryType({x:#X})"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+#[repr(transparent)]
+pub struct MemoryAttribute(pub u64);
+
+impl MemoryAttribute {
+    pub const NONE: Self = Self(0);
+    pub const UC: Self = Self(0x1);
+    pub const WC: Self = Self(0x2);
+    pub const WT: Self = Self(0x4);
+    pub const WB: Self = Self(0x8);
+    pub const UCE: Self = Self(0x10);
+    pub const WP: Self = Self(0x1000);
+    pub const RP: Self = Self(0x2000);
+    pub const XP: Self = Self(0x4000);
+    pub const NV: Self = Self(0x8000);
+    pub const MORE_RELIABLE: Self = Self(0x10000);
+    pub const RO: Self = Self(0x20000);
+    pub const SP: Self = Self(0x40000);
+    pub const CPU_CRYPTO: Self = Self(0x80000);
+    pub const RUNTIME: Self = Self(0x8000_0000_0000_0000);
+
+    const NAMES: [(Self, &str); 14] = [
+        (Self::UC, "UC"),
+        (Self::WC, "WC"),
+        (Self::WT, "WT"),
+        (Self::WB, "WB"),
+        (Self::UCE, "UCE"),
+        (Self::WP, "WP"),
+        (Self::RP, "RP"),
+        (Self::XP, "XP"),
+        (Self::NV, "NV"),
+        (Self::MORE_RELIABLE, "MORE_RELIABLE"),
+        (Self::RO, "RO"),
+        (Self::SP, "SP"),
+        (Self::CPU_CRYPTO, "CPU_CRYPTO"),
+        (Self::RUNTIME, "RUNTIME"),
+    ];
+
+    pub const fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl BitOr for MemoryAttribute {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self::Output {
+        Self(self.0 | rhs.0)
+    }
+}
+
+impl Debug for MemoryAttribute {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let mut remaining = self.0;
+        let mut first = true;
+        for (attribute, name) in Self::NAMES {
+            if self.contains(attribute) {
+                if !first {
+                    write!(f, " | ")?;
+                }
+                write!(f, "{name}")?;
+                remaining &= !attribute.0;
+                first = false;
+            }
+        }
+        if remaining != 0 || first {
+            if !first {
+                write!(f, " | ")?;
+            }
+            write!(f, "{remaining:#X}")?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+#[repr(C)]
+pub struct MemoryDescriptor {
+    pub memory_type: MemoryType,
+    pub physical_start: usize,
+    pub virtual_start: usize,
+    pub number_of_pages: usize,
+    pub attribute: MemoryAttribute,
+}
+
+impl MemoryDescriptor {
+    pub const fn size(&self) -> usize {
+        self.number_of_pages * 4096
+    }
+
+    pub const fn physical_end(&self) -> usize {
+        self.physical_start + self.size()
+    }
+}
+
+const MEMORY_MAP_ATTEMPTS: usize = 8;
+const MEMORY_MAP_HEADROOM: usize = 8;
+
+#[derive(Debug, Clone, Copy)]
+pub enum MemoryMapError {
+    GetMemoryMap {
+        error: Error,
+        attempts: usize,
+        buffer_size: usize,
+        required_size: usize,
+    },
+    AllocatePages {
+        error: Error,
+        pages: usize,
+    },
+    FreePages {
+        error: Error,
+        pages: usize,
+    },
+    ExitBootServices {
+        error: Error,
+        attempts: usize,
+        map_key: usize,
+        descriptor_count: usize,
+    },
+}
+
+impl MemoryMapError {
+    pub fn error(self) -> Error {
+        match self {
+            Self::GetMemoryMap { error, .. }
+            | Self::AllocatePages { error, .. }
+            | Self::FreePages { error, .. }
+            | Self::ExitBootServices { error, .. } => error,
+        }
+    }
+}
+
+#[repr(C)]
+pub struct MemoryMap {
+    buffer: NonNull<u8>,
+    buffer_pages: usize,
+    size: usize,
+    map_key: usize,
+    descriptor_size: usize,
+    descriptor_version: u32,
+}
+
+unsafe impl Send for MemoryMap {}
+unsafe impl Sync for MemoryMap {}
+
+impl MemoryMap {
+    pub const fn empty() -> Self {
+        Self {
+            buffer: NonNull::dangling(),
+            buffer_pages: 0,
+            size: 0,
+            map_key: 0,
+            descriptor_size: size_of::<MemoryDescriptor>(),
+            descriptor_version: 0,
+        }
+    }
+
+    pub unsafe fn new(system_table: SystemTable) -> Result<Self, MemoryMapError> {
+        let mut memory_map = Self::empty();
+        unsafe { memory_map.refresh(system_table)? };
+        Ok(memory_map)
+    }
+
+    // fetches the current memory map again, growing the buffer if it no longer fits
+    pub unsafe fn refresh(&mut self, system_table: SystemTable) -> Result<(), MemoryMapError> {
+        unsafe { self.fetch(system_table, true) }
+    }
+
+    // after a failed ExitBootServices only GetMemoryMap can be called, so the buffer can't be grown
+    // and the map has to fit in the headroom it was allocated with
+    unsafe fn fetch(
+        &mut self,
+        system_table: SystemTable,
+        grow: bool,
+    ) -> Result<(), MemoryMapError> {
+        for attempt in 1..=MEMORY_MAP_ATTEMPTS {
+            let buffer_size = self.buffer_pages * 4096;
+            let buffer = if self.buffer_pages == 0 {
+                core::ptr::null_mut()
+            } else {
+                self.buffer.as_ptr().cast()
+            };
+
+            let mut memory_map_size = buffer_size;
+            let mut map_key = 0;
+            let mut descriptor_size = 0;
+            let mut descriptor_version = 0;
+            match unsafe {
+                system_table.get_memory_map(
+                    &mut memory_map_size,
+                    buffer,
+                    &mut map_key,
+                    &mut descriptor_size,
+                    &mut descriptor_version,
+                )
+            } {
+                Ok(()) => {
+                    assert!(descriptor_size >= size_of::<MemoryDescriptor>());
+                    assert!(descriptor_size.is_multiple_of(align_of::<MemoryDescriptor>()));
+                    assert!(memory_map_size.is_multiple_of(descriptor_size));
+
+                    self.size = memory_map_size;
+                    self.map_key = map_key;
+                    self.descriptor_size = descriptor_size;
+                    self.descriptor_version = descriptor_version;
+                    return Ok(());
+                }
+
+                Err(Error::BUFFER_TOO_SMALL) if !grow => {
+                    return Err(MemoryMapError::GetMemoryMap {
+                        error: Error::BUFFER_TOO_SMALL,
+                        attempts: attempt,
+                        buffer_size,
+                        required_size: memory_map_size,
+                    });
+                }
+
+                Err(Error::BUFFER_TOO_SMALL) => {
+                    if self.buffer_pages != 0 {
+                        unsafe { system_table.free_pages(buffer.cast(), self.buffer_pages) }
+                            .map_err(|error| MemoryMapError::FreePages {
+                                error,
+                                pages: self.buffer_pages,
+                            })?;
+                        self.buffer_pages = 0;
+                        self.size = 0;
+                    }
+
+                    // allocating the buffer can split a free region into more descriptors, so leave some room
+                    let descriptor_size = descriptor_size.max(size_of::<MemoryDescriptor>());
+                    let pages =
+                        (memory_map_size + MEMORY_MAP_HEADROOM * descriptor_size).div_ceil(4096);
+                    let buffer = unsafe {
+                        system_table.allocate_pages(
+                            AllocateType::AnyPages,
+                            MemoryType::LoaderData,
+                            pages,
+                        )
+                    }
+                    .map_err(|error| MemoryMapError::AllocatePages { error, pages })?;
+
+                    self.buffer =
+                        NonNull::new(buffer.cast()).ok_or(MemoryMapError::AllocatePages {
+                            error: Error::OUT_OF_RESOURCES,
+                            pages,
+                        })?;
+                    self.buffer_pages = pages;
+                }
+
+                Err(error) => {
+                    return Err(MemoryMapError::GetMemoryMap {
+                        error,
+                        attempts: attempt,
+                        buffer_size,
+                        required_size: memory_map_size,
+                    });
+                }
+            }
+        }
+
+        Err(MemoryMapError::GetMemoryMap {
+            error: Error::BUFFER_TOO_SMALL,
+            attempts: MEMORY_MAP_ATTEMPTS,
+            buffer_size: self.buffer_pages * 4096,
+            required_size: self.size,
+        })
+    }
+
+    // on success boot services are gone, and the returned map is the final one
+    pub unsafe fn exit_boot_services(
+        system_table: SystemTable,
+        image_handle: Handle,
+    ) -> Result<Self, MemoryMapError> {
+        let mut memory_map = unsafe { Self::new(system_table)? };
+        let mut attempt = 1;
+        loop {
+            match unsafe { system_table.exit_boot_services(image_handle, memory_map.map_key) } {
+                Ok(()) => return Ok(memory_map),
+
+                // the map key is stale, the spec says to get the map again and retry
+                Err(Error::INVALID_PARAMETER) if attempt < MEMORY_MAP_ATTEMPTS => {
+                    unsafe { memory_map.fetch(system_table, false)? };
+                    attempt += 1;
+                }
+
+                Err(error) => {
+                    return Err(MemoryMapError::ExitBootServices {
+                        error,
+                        attempts: attempt,
+                        map_key: memory_map.map_key,
+                        descriptor_count: memory_map.len(),
+                    });
+                }
+            }
+        }
+    }
+
+    pub fn map_key(&self) -> usize {
+        self.map_key
+    }
+
+    pub fn descriptor_version(&self) -> u32 {
+        self.descriptor_version
+    }
+
+    // the pages the descriptors themselves are stored in
+    pub fn buffer_range(&self) -> Range<usize> {
+        let start = self.buffer.as_ptr().addr();
+        start..start + self.buffer_pages * 4096
+    }
+
+    pub fn len(&self) -> usize {
+        self.size / self.descriptor_size
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn get(&self, index: usize) -> Option<&MemoryDescriptor> {
+        if index < self.len() {
+            Some(unsafe {
+                &*self
+                    .buffer
+                    .as_ptr()
+                    .add(index * self.descriptor_size)
+                    .cast::<MemoryDescriptor>()
+            })
+        } else {
+            None
+        }
+    }
+
+    fn get_mut(&mut self, index: usize) -> Option<&mut MemoryDescriptor> {
+        if index < self.len() {
+            Some(unsafe {
+                &mut *self
+                    .buffer
+                    .as_ptr()
+                    .add(index * self.descriptor_size)
+                    .cast::<MemoryDescriptor>()
+            })
+        } else {
+            None
+        }
+    }
+
+    pub fn iter(&self) -> MemoryMapIter<'_> {
+        MemoryMapIter {
+            memory_map: self,
+            index: 0,
+        }
+    }
+
+    fn bytes_mut(&mut self) -> &mut [u8] {
+        unsafe { core::slice::from_raw_parts_mut(self.buffer.as_ptr(), self.size) }
+    }
+
+    fn swap(&mut self, a: usize, b: usize) {
+        let (a, b) = (a.min(b), a.max(b));
+        if a == b {
+            return;
+        }
+        let descriptor_size = self.descriptor_size;
+        let (left, right) = self.bytes_mut().split_at_mut(b * descriptor_size);
+        left[a * descriptor_size..][..descriptor_size]
+            .swap_with_slice(&mut right[..descriptor_size]);
+    }
+
+    pub fn sort(&mut self) {
+        for i in 1..self.len() {
+            let mut j = i;
+            while j > 0 && self[j - 1].physical_start > self[j].physical_start {
+                self.swap(j - 1, j);
+                j -= 1;
+            }
+        }
+    }
+
+    // combines descriptors that are next to each other with the same type and attributes, this expects the map to be sorted
+    pub fn merge(&mut self) {
+        let mut i = 0;
+        while i + 1 < self.len() {
+            let next = self[i + 1];
+            let current = self.get_mut(i).unwrap();
+            if current.physical_end() == next.physical_start
+                && current.memory_type == next.memory_type
+                && current.attribute == next.attribute
+            {
+                current.number_of_pages += next.number_of_pages;
+
+                let descriptor_size = self.descriptor_size;
+                let start = (i + 2) * descriptor_size;
+                self.bytes_mut()
+                    .copy_within(start.., start - descriptor_size);
+                self.size -= descriptor_size;
+            } else {
+                i += 1;
+            }
+        }
+    }
+}
+
+impl core::ops::Index<usize> for MemoryMap {
+    type Output = MemoryDescriptor;
+
+    fn index(&self, index: usize) -> &Self::Output {
+        self.get(index)
+            .expect("the index should be inside the memory map")
+    }
+}
+
+impl<'a> IntoIterator for &'a MemoryMap {
+    type Item = &'a MemoryDescriptor;
+    type IntoIter = MemoryMapIter<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+pub struct MemoryMapIter<'a> {
+    memory_map: &'a MemoryMap,
+    index: usize,
+}
+
+impl<'a> Iterator for MemoryMapIter<'a> {
+    type Item = &'a MemoryDescriptor;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let memory_descriptor = self.memory_map.get(self.index)?;
+        self.index += 1;
+        Some(memory_descriptor)
+    }
+}
+
+#[repr(C)]
+struct ConfigurationTable {
+    vendor_guid: Guid,
+    vendor_table: *const (),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(C, align(8))]
+pub struct Guid {
+    data1: u32,
+    data2: u16,
+    data3: u16,
+    data4: [u8; 8],
+}
+
+impl Guid {
+    pub const GRAPHICS_OUTPUT_PROTOCOL: Self = Self {
+        data1: 0x9042a9de,
+        data2: 0x23dc,
+        data3: 0x4a38,
+        data4: [0x96, 0xfb, 0x7a, 0xde, 0xd0, 0x80, 0x51, 0x6a],
+    };
+
+    pub const LOADED_IMAGE_PROTOCOL: Self = Self {
+        data1: 0x5b1b31a1,
+        data2: 0x9562,
+        data3: 0x11d2,
+        data4: [0x8e, 0x3f, 0x00, 0xa0, 0xc9, 0x69, 0x72, 0x3b],
+    };
+
+    pub const SIMPLE_FILE_SYSTEM_PROTOCOL: Self = Self {
+        data1: 0x964e5b22,
+        data2: 0x6459,
+        data3: 0x11d2,
+        data4: [0x8e, 0x39, 0x00, 0xa0, 0xc9, 0x69, 0x72, 0x3b],
+    };
+
+    pub const FILE_INFO: Self = Self {
+        data1: 0x09576e92,
+        data2: 0x6d3f,
+        data3: 0x11d2,
+        data4: [0x8e, 0x39, 0x00, 0xa0, 0xc9, 0x69, 0x72, 0x3b],
+    };
+
+    pub const ACPI_TABLE: Self = Self {
+        data1: 0xeb9d2d30,
+        data2: 0x2d88,
+        data3: 0x11d3,
+        data4: [0x9a, 0x16, 0x00, 0x90, 0x27, 0x3f, 0xc1, 0x4d],
+    };
+
+    pub const ACPI_20_TABLE: Self = Self {
+        data1: 0x8868e871,
+        data2: 0xe4f1,
+        data3: 0x11d3,
+        data4: [0xbc, 0x22, 0x00, 0x80, 0xc7, 0x3c, 0x88, 0x81],
+    };
+}
+
+#[derive(Debug, Clone, Copy)]
+#[repr(transparent)]
+pub struct LoadedImage(*const LoadedImageData);
+
+impl LoadedImage {
+    // the device the image was loaded from
+    pub fn device_handle(self) -> Handle {
+        unsafe { (*self.0).device_handle }
+    }
+
+    // where the firmware loaded this image
+    pub fn image_base(self) -> *const () {
+        unsafe { (*self.0).image_base }
+    }
+
+    pub fn image_size(self) -> usize {
+        unsafe { (*self.0).image_size as usize }
+    }
+
+    // the raw options the image was started with, usually a UCS-2 command line
+    pub fn load_options(self) -> &'static [u8] {
+        unsafe {
+            let data = &*self.0;
+            if data.load_options.is_null() {
+                &[]
+            } else {
+                core::slice::from_raw_parts(
+                    data.load_options.cast(),
+                    data.load_options_size as usize,
+                )
+            }
+        }
+    }
+}
+
+#[repr(C)]
+struct LoadedImageData {
+    revision: u32,
+    parent_handle: Handle,
+    system_table: *const SystemTableData,
+    device_handle: Handle,
+    file_path: *const (),
+    reserved: *const (),
+    load_options_size: u32,
+    load_options: *const (),
+    image_base: *const (),
+    image_size: u64,
+    image_code_type: MemoryType,
+    image_data_type: MemoryType,
+    unload: unsafe extern "efiapi" fn(),
+}
+
+#[derive(Debug, Clone, Copy)]
+#[repr(transparent)]
+pub struct SimpleFileSystem(*const SimpleFileSystemData);
+
+impl SimpleFileSystem {
+    pub fn open_volume(self) -> Result<File, Error> {
+        let mut root = core::ptr::null();
+        unsafe { ((*self.0).open_volume)(self.0, &raw mut root)? };
+        Ok(File(root))
+    }
+}
+
+#[repr(C)]
+struct SimpleFileSystemData {
+    revision: u64,
+    open_volume: unsafe extern "efiapi" fn(
+        this: *const SimpleFileSystemData,
+        root: *mut *const FileData,
+    ) -> Status,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+#[repr(transparent)]
+pub struct FileMode(pub u64);
+
+impl FileMode {
+    pub const READ: Self = Self(0x1);
+    pub const WRITE: Self = Self(0x2);
+    pub const CREATE: Self = Self(0x8000_0000_0000_0000);
+}
+
+impl BitOr for FileMode {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self::Output {
+        Self(self.0 | rhs.0)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+#[repr(transparent)]
+pub struct FileAttribute(pub u64);
+
+impl FileAttribute {
+    pub const NONE: Self = Self(0);
+    pub const READ_ONLY: Self = Self(0x1);
+    pub const HIDDEN: Self = Self(0x2);
+    pub const SYSTEM: Self = Self(0x4);
+    pub const RESERVED: Self = Self(0x8);
+    pub const DIRECTORY: Self = Self(0x10);
+    pub const ARCHIVE: Self = Self(0x20);
+
+    const NAMES: [(Self, &str); 6] = [
+        (Self::READ_ONLY, "READ_ONLY"),
+        (Self::HIDDEN, "HIDDEN"),
+        (Self::SYSTEM, "SYSTEM"),
+        (Self::RESERVED, "RESERVED"),
+        (Self::DIRECTORY, "DIRECTORY"),
+        (Self::ARCHIVE, "ARCHIVE"),
+    ];
+
+    pub const fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl BitOr for FileAttribute {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self::Output {
+        Self(self.0 | rhs.0)
+    }
+}
+
+impl Debug for FileAttribute {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let mut first = true;
+        for (attribute, name) in Self::NAMES {
+            if self.contains(attribute) {
+                if !first {
+                    write!(f, " | ")?;
+                }
+                write!(f, "{name}")?;
+                first = false;
+            }
+        }
+        if first {
+            write!(f, "NONE")?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+#[repr(C)]
+pub struct Time {
+    pub year: u16,
+    pub month: u8,
+    pub day: u8,
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+    pad1: u8,
+    pub nanosecond: u32,
+    pub time_zone: i16,
+    pub daylight: u8,
+    pad2: u8,
+}
+
+#[derive(Clone, Copy)]
+#[repr(C)]
+struct FileInfoHeader {
+    size: u64,
+    file_size: u64,
+    physical_size: u64,
+    create_time: Time,
+    last_access_time: Time,
+    modification_time: Time,
+    attribute: FileAttribute,
+}
+
+// an EFI_FILE_INFO, which is a fixed header followed by the null terminated file name
+#[derive(Clone, Copy)]
+pub struct FileInfo<'a> {
+    header: FileInfoHeader,
+    file_name: &'a [u8],
+}
+
+impl<'a> FileInfo<'a> {
+    // big enough for any file name FAT allows
+    pub const BUFFER_SIZE: usize = size_of::<FileInfoHeader>() + 256 * size_of::<u16>();
+
+    fn parse(bytes: &'a [u8]) -> Result<Self, Error> {
+        if bytes.len() < size_of::<FileInfoHeader>() {
+            return Err(Error::BAD_BUFFER_SIZE);
+        }
+        let header = unsafe { bytes.as_ptr().cast::<FileInfoHeader>().read_unaligned() };
+        let size = (header.size as usize).clamp(size_of::<FileInfoHeader>(), bytes.len());
+        Ok(Self {
+            header,
+            file_name: &bytes[size_of::<FileInfoHeader>()..size],
+        })
+    }
+
+    pub fn file_size(&self) -> u64 {
+        self.header.file_size
+    }
+
+    pub fn physical_size(&self) -> u64 {
+        self.header.physical_size
+    }
+
+    pub fn create_time(&self) -> Time {
+        self.header.create_time
+    }
+
+    pub fn last_access_time(&self) -> Time {
+        self.header.last_access_time
+    }
+
+    pub fn modification_time(&self) -> Time {
+        self.header.modification_time
+    }
+
+    pub fn attribute(&self) -> FileAttribute {
+        self.header.attribute
+    }
+
+    pub fn is_directory(&self) -> bool {
+        self.attribute().contains(FileAttribute::DIRECTORY)
+    }
+
+    pub fn file_name(&self) -> impl Iterator<Item = char> + 'a {
+        let units = self
+            .file_name
+            .chunks_exact(2)
+            .map(|unit| u16::from_le_bytes([unit[0], unit[1]]))
+            .take_while(|&unit| unit != 0);
+        char::decode_utf16(units).map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+    }
+}
+
+impl Debug for FileInfo<'_> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        struct FileName<'a>(FileInfo<'a>);
+
+        impl Debug for FileName<'_> {
+            fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+                write!(f, "\"")?;
+                for c in self.0.file_name() {
+                    write!(f, "{}", c.escape_debug())?;
+                }
+                write!(f, "\"")
+            }
+        }
+
+        f.debug_struct("FileInfo")
+            .field("file_name", &FileName(*self))
+            .field("file_size", &self.file_size())
+            .field("physical_size", &self.physical_size())
+            .field("attribute", &self.attribute())
+            .field("modification_time", &self.modification_time())
+            .finish()
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+#[repr(transparent)]
+pub struct File(*const FileData);
+
+impl File {
+    // `path` is a null terminated UCS-2 path relative to this file, using '\' as the separator
+    // `attributes` are only used when the file gets created
+    pub fn open(
+        self,
+        path: &[u16],
+        mode: FileMode,
+        attributes: FileAttribute,
+    ) -> Result<File, Error> {
+        assert_eq!(path.last(), Some(&0), "the path should be null terminated");
+        let mut file = core::ptr::null();
+        unsafe { ((*self.0).open)(self.0, &raw mut file, path.as_ptr(), mode.0, attributes.0)? };
+        Ok(File(file))
+    }
+
+    pub fn close(self) -> Status {
+        unsafe { ((*self.0).close)(self.0) }
+    }
+
+    // closes the file and deletes it
+    pub fn delete(self) -> Status {
+        unsafe { ((*self.0).delete)(self.0) }
+    }
+
+    // returns how many bytes were read, which is 0 at the end of the file
+    pub fn read(self, buffer: &mut [u8]) -> Result<usize, Error> {
+        let mut size = buffer.len();
+        unsafe { ((*self.0).read)(self.0, &raw mut size, buffer.as_mut_ptr().cast())? };
+        Ok(size)
+    }
+
+    // reading a directory gives one entry at a time, `None` once all of them have been read
+    // `buffer` should be at least `FileInfo::BUFFER_SIZE` bytes
+    pub fn read_entry<'a>(self, buffer: &'a mut [u8]) -> Result<Option<FileInfo<'a>>, Error> {
+        let size = self.read(buffer)?;
+        if size == 0 {
+            return Ok(None);
+        }
+        FileInfo::parse(&buffer[..size]).map(Some)
+    }
+
+    // returns how many bytes were written
+    pub fn write(self, buffer: &[u8]) -> Result<usize, Error> {
+        let mut size = buffer.len();
+        unsafe { ((*self.0).write)(self.0, &raw mut size, buffer.as_ptr().cast())? };
+        Ok(size)
+    }
+
+    pub fn write_all(self, mut buffer: &[u8]) -> Status {
+        while !buffer.is_empty() {
+            let written = self.write(buffer)?;
+            if written == 0 {
+                return Err(Error::DEVICE_ERROR);
+            }
+            buffer = &buffer[written..];
+        }
+        Ok(())
+    }
+
+    pub fn flush(self) -> Status {
+        unsafe { ((*self.0).flush)(self.0) }
+    }
+
+    pub fn position(self) -> Result<u64, Error> {
+        let mut position = 0;
+        unsafe { ((*self.0).get_position)(self.0, &raw mut position)? };
+        Ok(position)
+    }
+
+    // `u64::MAX` moves to the end of the file
+    pub fn set_position(self, position: u64) -> Status {
+        unsafe { ((*self.0).set_position)(self.0, position) }
+    }
+
+    // `buffer` should be at least `FileInfo::BUFFER_SIZE` bytes
+    pub fn info<'a>(self, buffer: &'a mut [u8]) -> Result<FileInfo<'a>, Error> {
+        let guid = Guid::FILE_INFO;
+        let mut size = buffer.len();
+        unsafe {
+            ((*self.0).get_info)(
+                self.0,
+                &raw const guid,
+                &raw mut size,
+                buffer.as_mut_ptr().cast(),
+            )?;
+        }
+        FileInfo::parse(&buffer[..size.min(buffer.len())])
+    }
+}
+
+#[repr(C)]
+struct FileData {
+    revision: u64,
+    open: unsafe extern "efiapi" fn(
+        this: *const FileData,
+        new_handle: *mut *const FileData,
+        file_name: *const u16,
+        open_mode: u64,
+        attributes: u64,
+    ) -> Status,
+    close: unsafe extern "efiapi" fn(this: *const FileData) -> Status,
+    delete: unsafe extern "efiapi" fn(this: *const FileData) -> Status,
+    read: unsafe extern "efiapi" fn(
+        this: *const FileData,
+        buffer_size: *mut usize,
+        buffer: *mut (),
+    ) -> Status,
+    write: unsafe extern "efiapi" fn(
+        this: *const FileData,
+        buffer_size: *mut usize,
+        buffer: *const (),
+    ) -> Status,
+    get_position: unsafe extern "efiapi" fn(this: *const FileData, position: *mut u64) -> Status,
+    set_position: unsafe extern "efiapi" fn(this: *const FileData, position: u64) -> Status,
+    get_info: unsafe extern "efiapi" fn(
+        this: *const FileData,
+        information_type: *const Guid,
+        buffer_size: *mut usize,
+        buffer: *mut (),
+    ) -> Status,
+    set_info: unsafe extern "efiapi" fn(),
+    flush: unsafe extern "efiapi" fn(this: *const FileData) -> Status,
+}
+
+#[derive(Debug, Clone, Copy)]
+#[repr(transparent)]
+pub struct GOP(*const GOPData);
+
+impl GOP {
+    // the firmware allocates the info from pool memory, which is copied out and given back
+    pub fn query_mode(
+        self,
+        system_table: SystemTable,
+        mode_number: u32,
+    ) -> Result<(usize, GOPModeInformation), Error> {
+        let mut size = 0;
+        let mut info = core::ptr::null();
+        unsafe {
+            ((*self.0).query_mode)(self.0, mode_number, &raw mut size, &raw mut info)?;
+        }
+        let mode_information = unsafe { *info };
+        _ = unsafe { system_table.free_pool(info.cast_mut().cast()) };
+        Ok((size, mode_information))
+    }
+
+    pub fn set_mode(self, mode_number: u32) -> Status {
+        unsafe { ((*self.0).set_mode)(self.0, mode_number) }
+    }
+
+    pub fn mode(self) -> Option<NonNull<GOPMode>> {
+        unsafe { NonNull::new((*self.0).mode.cast_mut()) }
+    }
+
+    pub fn current_mode(self) -> Option<u32> {
+        self.mode().map(|mode| unsafe { (*mode.as_ptr()).mode })
+    }
+
+    pub fn max_mode(self) -> u32 {
+        self.mode()
+            .map_or(0, |mode| unsafe { (*mode.as_ptr()).max_mode })
+    }
+
+    // every mode the firmware reports, skipping any that fail to be queried
+    pub fn modes(
+        self,
+        system_table: SystemTable,
+    ) -> impl Iterator<Item = (u32, GOPModeInformation)> {
+        (0..self.max_mode()).filter_map(move |mode_number| {
+            self.query_mode(system_table, mode_number)
+                .ok()
+                .map(|(_, info)| (mode_number, info))
+        })
+    }
+}
+
+#[repr(C)]
+struct GOPData {
+    query_mode: unsafe extern "efiapi" fn(
+        this: *const GOPData,
+        mode_number: u32,
+        size_of_info: *mut usize,
+        info: *mut *const GOPModeInformation,
+    ) -> Status,
+    set_mode: unsafe extern "efiapi" fn(this: *const GOPData, mode_number: u32) -> Status,
+    blt: unsafe extern "efiapi" fn(),
+    mode: *const GOPMode,
+}
+
+#[derive(Debug, Clone, Copy)]
+#[repr(C)]
+pub struct GOPMode {
+    pub max_mode: u32,
+    pub mode: u32,
+    pub info: *const GOPModeInformation,
+    pub size_of_info: usize,
+    pub frame_buffer_base: *mut (),
+    pub frame_buffer_size: usize,
+}
+
+#[derive(Debug, Clone, Copy)]
+#[repr(C)]
+pub struct GOPModeInformation {
+    pub version: u32,
+    pub horizontal_resolution: u32,
+    pub vertical_resolution: u32,
+    pub pixel_format: GraphicsPixelFormat,
+    pub pixel_information: PixelBitmask,
+    pub pixels_per_scan_line: u32,
+}
+
+#[derive(Debug, Clone, Copy)]
+#[repr(C)]
+pub enum GraphicsPixelFormat {
+    RedGreenBlueReserved8BitPerColor,
+    BlueGreenRedReserved8BitPerColor,
+    BitMask,
+    BltOnly,
+}
+
+#[derive(Debug, Clone, Copy)]
+#[repr(C)]
+pub struct PixelBitmask {
+    pub red_mask: u32,
+    pub green_mask: u32,
+    pub blue_mask: u32,
+    pub reserved_mask: u32,
+}
@@ -3,7 +3,11 @@
 use core::ffi::CStr;
 
 const SPACE_MONO_FNT: &[u8] = include_bytes!("./font_data/space_mono.fnt");
-const SPACE_MONO_TGA_0: &[u8] = include_bytes!("./font_data/space_mono_0.tga");
+// the textures for each file name in the page block of the font
+const SPACE_MONO_TEXTURES: &[(&str, &[u8])] = &[(
+    "space_mono_0.tga",
+    include_bytes!("./font_data/space_mono_0.tga"),
+)];
 
 pub struct Info<'a> {
     pub font_size: u16,
@@ -44,14 +48,22 @@ pub struct Char {
     pub y: u16,
     pub width: u16,
     pub height: u16,
-    pub xoffset: u16,
-    pub yoffset: u16,
+    pub xoffset: i16,
+    pub yoffset: i16,
     pub xadvance: u16,
     pub page: u8,
     pub chnl: u8,
 }
 
+// how much closer or further apart `second` goes when it comes right after `first`
+pub struct KerningPair {
+    pub first: u32,
+    pub second: u32,
+    pub amount: i16,
+}
+
 pub struct Page<'a> {
+    pub name: &'a CStr,
     pub width: u16,
     pub height: u16,
     pub brightnesses: &'a [u8],
@@ -61,14 +73,266 @@ pub struct Font<'a> {
     pub info: Info<'a>,
     pub common: Common,
     pub chars: &'a [Char],
+    // sorted by `first` then `second`
+    pub kerning_pairs: &'a [KerningPair],
     pub pages: &'a [Page<'a>],
 }
 
+impl Font<'_> {
+    pub fn char(&self, c: char) -> Option<&Char> {
+        self.chars
+            .binary_search_by_key(&(c as u32), |char| char.id)
+            .ok()
+            .map(|index| &self.chars[index])
+    }
+
+    pub fn kerning(&self, first: char, second: char) -> i16 {
+        self.kerning_pairs
+            .binary_search_by_key(&(first as u32, second as u32), |pair| {
+                (pair.first, pair.second)
+            })
+            .map_or(0, |index| self.kerning_pairs[index].amount)
+    }
+
+    // how far along the next character goes after `c`, and after `previous` before it if there was one,
+    // so rendering and measuring end up in the same place
+    pub fn advance(&self, previous: Option<char>, c: char) -> isize {
+        let kerning = previous.map_or(0, |previous| self.kerning(previous, c));
+        self.char(c)
+            .map_or(0, |char| char.xadvance as isize + kerning as isize)
+    }
+
+    // the width of the widest line, going by advances rather than the pixels drawn
+    pub fn advance_width(&self, text: &str) -> usize {
+        text.split('\n')
+            .map(|line| self.line_width(line))
+            .max()
+            .unwrap_or(0)
+    }
+
+    // the pixels that drawing the text at 0, 0 would touch
+    pub fn bounding_box(&self, text: &str) -> Bounds {
+        let mut bounds: Option<Bounds> = None;
+        for (row, line) in text.split('\n').enumerate() {
+            let y = row * self.common.line_height as usize;
+            self.for_each_glyph(line, |x, char| {
+                if char.width == 0 || char.height == 0 {
+                    return;
+                }
+                let glyph = Bounds {
+                    x: x.saturating_add_signed(char.xoffset as isize),
+                    y: y.saturating_add_signed(char.yoffset as isize),
+                    width: char.width as usize,
+                    height: char.height as usize,
+                };
+                bounds = Some(bounds.map_or(glyph, |bounds| bounds.union(glyph)));
+            });
+        }
+        bounds.unwrap_or_default()
+    }
+
+    pub fn line_count(&self, text: &str, max_width: Option<usize>) -> usize {
+        self.lines(text, max_width).count()
+    }
+
+    // `max_width` is what lines get wrapped to, and what they are aligned within,
+    // without one only `\n` starts a new line and they are aligned within the widest line
+    pub fn layout<'a>(
+        &'a self,
+        text: &'a str,
+        max_width: Option<usize>,
+        alignment: Alignment,
+    ) -> Layout<'a> {
+        let mut widest = 0;
+        let mut line_count = 0;
+        for line in self.lines(text, max_width) {
+            widest = widest.max(self.line_width(line));
+            line_count += 1;
+        }
+
+        Layout {
+            font: self,
+            text,
+            max_width,
+            alignment,
+            width: max_width.unwrap_or(widest),
+            line_count,
+        }
+    }
+
+    fn lines<'a>(&'a self, text: &'a str, max_width: Option<usize>) -> Lines<'a> {
+        Lines {
+            font: self,
+            rest: Some(text),
+            max_width: max_width.unwrap_or(usize::MAX),
+        }
+    }
+
+    // spaces at the end are left out, so a wrapped line doesn't look shorter than it is
+    fn line_width(&self, line: &str) -> usize {
+        let mut width = 0;
+        self.for_each_glyph(line.trim_end_matches(' '), |x, char| {
+            width = x + char.xadvance as usize;
+        });
+        width
+    }
+
+    // the same positions `TextWriter` puts each glyph at, with characters the font doesn't have skipped
+    fn for_each_glyph(&self, line: &str, mut f: impl FnMut(usize, &Char)) {
+        let mut x = 0usize;
+        let mut previous = None;
+        for c in line.chars() {
+            if let Some(char) = self.char(c) {
+                if let Some(previous) = previous {
+                    x = x.saturating_add_signed(self.kerning(previous, c) as isize);
+                }
+                f(x, char);
+                x += char.xadvance as usize;
+                previous = Some(c);
+            }
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Bounds {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Bounds {
+    pub fn union(self, other: Self) -> Self {
+        let x = self.x.min(other.x);
+        let y = self.y.min(other.y);
+        Self {
+            x,
+            y,
+            width: (self.x + self.width).max(other.x + other.width) - x,
+            height: (self.y + self.height).max(other.y + other.height) - y,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Alignment {
+    Left,
+    Center,
+    Right,
+}
+
+// text split into lines, which are only worked out again as they're iterated over
+#[derive(Clone, Copy)]
+pub struct Layout<'a> {
+    pub font: &'a Font<'a>,
+    pub text: &'a str,
+    pub max_width: Option<usize>,
+    pub alignment: Alignment,
+    // what the lines are aligned within
+    pub width: usize,
+    pub line_count: usize,
+}
+
+impl<'a> Layout<'a> {
+    pub fn height(&self) -> usize {
+        self.line_count * self.font.common.line_height as usize
+    }
+
+    pub fn lines(&self) -> impl Iterator<Item = LayoutLine<'a>> {
+        let font = self.font;
+        let width = self.width;
+        let alignment = self.alignment;
+        font.lines(self.text, self.max_width)
+            .enumerate()
+            .map(move |(row, text)| {
+                let line_width = font.line_width(text);
+                let space = width.saturating_sub(line_width);
+                LayoutLine {
+                    text,
+                    x: match alignment {
+                        Alignment::Left => 0,
+                        Alignment::Center => space / 2,
+                        Alignment::Right => space,
+                    },
+                    y: row * font.common.line_height as usize,
+                    width: line_width,
+                }
+            })
+    }
+}
+
+// `x` and `y` are relative to the top left of the layout
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LayoutLine<'a> {
+    pub text: &'a str,
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+}
+
+// breaks after spaces when a line would go past `max_width`, or anywhere for words that don't fit on a line at all
+struct Lines<'a> {
+    font: &'a Font<'a>,
+    // `None` once the last line has been given out, so text ending in `\n` still has an empty line after it
+    rest: Option<&'a str>,
+    max_width: usize,
+}
+
+impl<'a> Iterator for Lines<'a> {
+    type Item = &'a str;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let text = self.rest.take()?;
+
+        let mut x = 0usize;
+        let mut previous = None;
+        // where the line ends and the next one starts if it gets wrapped at the last space
+        let mut space = None;
+        for (index, c) in text.char_indices() {
+            if c == '\n' {
+                self.rest = Some(&text[index + 1..]);
+                return Some(&text[..index]);
+            }
+            if c == ' ' {
+                space = Some(index);
+            }
+
+            let Some(char) = self.font.char(c) else {
+                continue;
+            };
+            if let Some(previous) = previous {
+                x = x.saturating_add_signed(self.font.kerning(previous, c) as isize);
+            }
+            x += char.xadvance as usize;
+
+            // spaces can hang off the end, they get dropped when the line is wrapped
+            if x > self.max_width && c != ' ' && previous.is_some() {
+                let (line, rest) = match space {
+                    Some(space) => (
+                        text[..space].trim_end_matches(' '),
+                        text[space..].trim_start_matches(' '),
+                    ),
+                    None => text.split_at(index),
+                };
+                if !rest.is_empty() {
+                    self.rest = Some(rest);
+                }
+                return Some(line);
+            }
+            previous = Some(c);
+        }
+
+        Some(text)
+    }
+}
+
 pub const SPACE_MONO: Font<'static> = Font {
     info: parse_info(SPACE_MONO_FNT),
     common: parse_common(SPACE_MONO_FNT),
     chars: &parse_chars::<{ chars_count(SPACE_MONO_FNT) }>(SPACE_MONO_FNT),
-    pages: &[parse_page(SPACE_MONO_TGA_0)],
+    kerning_pairs: &parse_kerning_pairs::<{ kerning_pairs_count(SPACE_MONO_FNT) }>(SPACE_MONO_FNT),
+    pages: &parse_pages::<{ pages_count(SPACE_MONO_FNT) }>(SPACE_MONO_FNT, SPACE_MONO_TEXTURES),
 };
 
 const fn parse_info(bytes: &[u8]) -> Info<'_> {
@@ -139,6 +403,7 @@ const fn parse_chars<const N: usize>(bytes: &[u8]) -> [Char; N] {
 
     let block = find_block(bytes, 4);
     assert!(block.len().is_multiple_of(20));
+    let pages = pages_count(bytes);
 
     {
         let mut i = 0;
@@ -155,12 +420,13 @@ const fn parse_chars<const N: usize>(bytes: &[u8]) -> [Char; N] {
                 y: u16::from_ne_bytes([block[index + 6], block[index + 7]]),
                 width: u16::from_ne_bytes([block[index + 8], block[index + 9]]),
                 height: u16::from_ne_bytes([block[index + 10], block[index + 11]]),
-                xoffset: u16::from_ne_bytes([block[index + 12], block[index + 13]]),
-                yoffset: u16::from_ne_bytes([block[index + 14], block[index + 15]]),
+                xoffset: i16::from_ne_bytes([block[index + 12], block[index + 13]]),
+                yoffset: i16::from_ne_bytes([block[index + 14], block[index + 15]]),
                 xadvance: u16::from_ne_bytes([block[index + 16], block[index + 17]]),
-                page: block[18],
-                chnl: block[19],
+                page: block[index + 18],
+                chnl: block[index + 19],
             };
+            assert!((chars[i].page as usize) < pages);
             i += 1;
         }
     }
@@ -181,7 +447,83 @@ const fn parse_chars<const N: usize>(bytes: &[u8]) -> [Char; N] {
     chars
 }
 
+// the kerning block is optional, monospace fonts don't need one
+const fn kerning_pairs_count(bytes: &[u8]) -> usize {
+    match try_find_block(bytes, 5) {
+        Some(block) => {
+            assert!(block.len().is_multiple_of(10));
+            block.len() / 10
+        }
+        None => 0,
+    }
+}
+
+const fn parse_kerning_pairs<const N: usize>(bytes: &[u8]) -> [KerningPair; N] {
+    let mut pairs = [const {
+        KerningPair {
+            first: 0,
+            second: 0,
+            amount: 0,
+        }
+    }; _];
+
+    let block = match try_find_block(bytes, 5) {
+        Some(block) => block,
+        None => &[],
+    };
+    assert!(block.len() == N * 10);
+
+    {
+        let mut i = 0;
+        while i < N {
+            let index = i * 10;
+            pairs[i] = KerningPair {
+                first: u32::from_ne_bytes([
+                    block[index],
+                    block[index + 1],
+                    block[index + 2],
+                    block[index + 3],
+                ]),
+                second: u32::from_ne_bytes([
+                    block[index + 4],
+                    block[index + 5],
+                    block[index + 6],
+                    block[index + 7],
+                ]),
+                amount: i16::from_ne_bytes([block[index + 8], block[index + 9]]),
+            };
+            i += 1;
+        }
+    }
+
+    // sort the pairs so a binary search can be done later, they usually come sorted already
+    {
+        let mut i = 1;
+        while i < N {
+            let mut j = i;
+            while j > 0
+                && (pairs[j - 1].first > pairs[j].first
+                    || (pairs[j - 1].first == pairs[j].first
+                        && pairs[j - 1].second > pairs[j].second))
+            {
+                pairs.swap(j - 1, j);
+                j -= 1;
+            }
+            i += 1;
+        }
+    }
+
+    pairs
+}
+
 const fn find_block(bytes: &[u8], id: u8) -> &[u8] {
+    match try_find_block(bytes, id) {
+        Some(block) => block,
+        None => panic!("there was no block with that id"),
+    }
+}
+
+const fn try_find_block(bytes: &[u8], id: u8) -> Option<&[u8]> {
     let mut index = 0;
 
     assert!(bytes[index] == b'B');
@@ -207,11 +549,97 @@ const fn find_block(bytes: &[u8], id: u8) -> &[u8] {
         let start = index;
         index += length;
         if found_id == id {
-            return bytes.split_at(start).1.split_at(length).0;
+            return Some(bytes.split_at(start).1.split_at(length).0);
+        }
+    }
+
+    None
+}
+
+// every name in the page block is followed by a nul
+const fn pages_count(bytes: &[u8]) -> usize {
+    let block = find_block(bytes, 3);
+    let mut count = 0;
+    let mut i = 0;
+    while i < block.len() {
+        if block[i] == 0 {
+            count += 1;
+        }
+        i += 1;
+    }
+    assert!(count == parse_common(bytes).pages as usize);
+    count
+}
+
+const fn page_name(bytes: &[u8], page: usize) -> &CStr {
+    let mut block = find_block(bytes, 3);
+    let mut i = 0;
+    loop {
+        let name = match CStr::from_bytes_until_nul(block) {
+            Ok(name) => name,
+            Err(_) => panic!(),
+        };
+        if i == page {
+            return name;
         }
+        block = block.split_at(name.count_bytes() + 1).1;
+        i += 1;
     }
+}
 
-    panic!("there was no block with that id")
+const fn parse_pages<'a, const N: usize>(
+    bytes: &'a [u8],
+    textures: &[(&str, &'a [u8])],
+) -> [Page<'a>; N] {
+    let common = parse_common(bytes);
+    let mut pages = [const {
+        Page {
+            name: c"",
+            width: 0,
+            height: 0,
+            brightnesses: &[],
+        }
+    }; _];
+
+    let mut i = 0;
+    while i < N {
+        let name = page_name(bytes, i);
+
+        let mut j = 0;
+        let texture = loop {
+            if j == textures.len() {
+                panic!("there was no texture for a page");
+            }
+            let (texture_name, texture) = textures[j];
+            if bytes_eq(texture_name.as_bytes(), name.to_bytes()) {
+                break texture;
+            }
+            j += 1;
+        };
+
+        pages[i] = Page {
+            name,
+            ..parse_page(texture)
+        };
+        assert!(pages[i].width == common.scale_w && pages[i].height == common.scale_h);
+        i += 1;
+    }
+
+    pages
+}
+
+const fn bytes_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    let mut i = 0;
+    while i < a.len() {
+        if a[i] != b[i] {
+            return false;
+        }
+        i += 1;
+    }
+    true
 }
 
 const fn parse_page(bytes: &[u8]) -> Page<'_> {
@@ -260,6 +688,7 @@ const fn parse_page(bytes: &[u8]) -> Page<'_> {
         .split_at(width as usize * height as usize)
         .0;
     Page {
+        name: c"",
         width,
         height,
         brightnesses,
@@ -0,0 +1,230 @@
+use crate::framebuffer::Color;
+
+const MAX_PARAMETERS: usize = 16;
+
+// the numbers between `ESC [` and the final byte, missing ones are 0
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Parameters {
+    values: [u16; MAX_PARAMETERS],
+    count: usize,
+}
+
+impl Parameters {
+    const fn new() -> Self {
+        Self {
+            values: [0; MAX_PARAMETERS],
+            count: 0,
+        }
+    }
+
+    pub fn as_slice(&self) -> &[u16] {
+        &self.values[..self.count]
+    }
+
+    // 0 counts as missing too, as it does for all the sequences that have a default
+    pub fn get_or(&self, index: usize, default: u16) -> u16 {
+        match self.as_slice().get(index) {
+            Some(&value) if value != 0 => value,
+            _ => default,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Sequence {
+    Print(char),
+    // c0 control characters like `\n`
+    Control(char),
+    // `ESC` followed by a single character, other than the ones that start longer sequences
+    Escape(char),
+    Csi {
+        parameters: Parameters,
+        // the `?` of sequences like `ESC [ ? 25 h`
+        private: Option<char>,
+        action: char,
+    },
+}
+
+#[derive(Debug, Clone, Copy)]
+enum State {
+    Ground,
+    Escape,
+    Csi {
+        parameters: Parameters,
+        private: Option<char>,
+        // sequences with intermediate bytes aren't supported, and get dropped once they finish
+        ignored: bool,
+    },
+    // operating system commands, like setting the window title, which are skipped over
+    Osc {
+        escape: bool,
+    },
+}
+
+const ESCAPE: char = '\x1B';
+const BELL: char = '\x07';
+// these cancel a sequence part way through
+const CANCEL: char = '\x18';
+const SUBSTITUTE: char = '\x1A';
+
+// splits text into characters and escape sequences, one character at a time
+#[derive(Debug, Clone, Copy)]
+pub struct Parser {
+    state: State,
+}
+
+impl Default for Parser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Parser {
+    pub const fn new() -> Self {
+        Self {
+            state: State::Ground,
+        }
+    }
+
+    pub fn advance(&mut self, c: char) -> Option<Sequence> {
+        match (&mut self.state, c) {
+            (State::Osc { .. }, BELL | CANCEL | SUBSTITUTE) => {
+                self.state = State::Ground;
+                None
+            }
+            (State::Osc { escape }, _) => {
+                // ended by `ESC \`
+                if *escape && c == '\\' {
+                    self.state = State::Ground;
+                } else {
+                    *escape = c == ESCAPE;
+                }
+                None
+            }
+
+            (_, ESCAPE) => {
+                self.state = State::Escape;
+                None
+            }
+            (_, CANCEL | SUBSTITUTE) => {
+                self.state = State::Ground;
+                None
+            }
+
+            (State::Ground, _) if c.is_control() => Some(Sequence::Control(c)),
+            (State::Ground, _) => Some(Sequence::Print(c)),
+
+            (State::Escape, '[') => {
+                self.state = State::Csi {
+                    parameters: Parameters::new(),
+                    private: None,
+                    ignored: false,
+                };
+                None
+            }
+            (State::Escape, ']') => {
+                self.state = State::Osc { escape: false };
+                None
+            }
+            (State::Escape, _) => {
+                self.state = State::Ground;
+                Some(Sequence::Escape(c))
+            }
+
+            // control characters still work in the middle of a sequence
+            (State::Csi { .. }, _) if c.is_control() => Some(Sequence::Control(c)),
+            (
+                State::Csi {
+                    parameters,
+                    private,
+                    ignored,
+                },
+                _,
+            ) => match c {
+                '0'..='9' => {
+                    if parameters.count == 0 {
+                        parameters.count = 1;
+                    }
+                    if let Some(value) = parameters.values.get_mut(parameters.count - 1) {
+                        *value = value
+                            .saturating_mul(10)
+                            .saturating_add(c as u16 - '0' as u16);
+                    }
+                    None
+                }
+                // colons are for the sub parameters of colours, which are close enough to separate ones
+                ';' | ':' => {
+                    parameters.count = (parameters.count.max(1) + 1).min(MAX_PARAMETERS);
+                    None
+                }
+                '<'..='?' if parameters.count == 0 && private.is_none() => {
+                    *private = Some(c);
+                    None
+                }
+                ' '..='/' | '<'..='?' => {
+                    *ignored = true;
+                    None
+                }
+                '@'..='~' => {
+                    let sequence = (!*ignored).then_some(Sequence::Csi {
+                        parameters: *parameters,
+                        private: *private,
+                        action: c,
+                    });
+                    self.state = State::Ground;
+                    sequence
+                }
+                _ => {
+                    self.state = State::Ground;
+                    None
+                }
+            },
+        }
+    }
+}
+
+// the 16 colours of xterm, then a 6 by 6 by 6 colour cube, then 24 shades of grey
+pub fn palette_color(index: u8) -> Color {
+    const COLORS: [(u8, u8, u8); 16] = [
+        (0, 0, 0),
+        (205, 0, 0),
+        (0, 205, 0),
+        (205, 205, 0),
+        (0, 0, 238),
+        (205, 0, 205),
+        (0, 205, 205),
+        (229, 229, 229),
+        (127, 127, 127),
+        (255, 0, 0),
+        (0, 255, 0),
+        (255, 255, 0),
+        (92, 92, 255),
+        (255, 0, 255),
+        (0, 255, 255),
+        (255, 255, 255),
+    ];
+    const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+    match index {
+        0..=15 => {
+            let (r, g, b) = COLORS[index as usize];
+            Color { r, g, b }
+        }
+        16..=231 => {
+            let index = index - 16;
+            Color {
+                r: CUBE_LEVELS[index as usize / 36],
+                g: CUBE_LEVELS[index as usize / 6 % 6],
+                b: CUBE_LEVELS[index as usize % 6],
+            }
+        }
+        _ => {
+            let grey = 8 + (index - 232) * 10;
+            Color {
+                r: grey,
+                g: grey,
+                b: grey,
+            }
+        }
+    }
+}
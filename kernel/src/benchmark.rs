@@ -0,0 +1,98 @@
+use crate::{
+    framebuffer::{Color, framebuffer},
+    screen::{FramebufferColorPixels, Rect, Screen},
+    simd::{SimdLevel, set_simd_level, simd_level, supported_simd_level},
+    text_writer::TextWriter,
+    utils::rdtsc,
+};
+use alloc::vec::Vec;
+use core::fmt::Write;
+use font::SPACE_MONO;
+
+const ITERATIONS: u64 = 16;
+
+#[derive(Debug, Clone, Copy)]
+pub struct BenchmarkResult {
+    pub name: &'static str,
+    pub level: SimdLevel,
+    // averaged over `ITERATIONS`
+    pub cycles: u64,
+}
+
+fn measure(mut f: impl FnMut()) -> u64 {
+    let start = rdtsc();
+    for _ in 0..ITERATIONS {
+        f();
+    }
+    (rdtsc() - start) / ITERATIONS
+}
+
+// times the row fast paths at every simd level the cpu has, enabled with `benchmark` on the command line
+// this draws over the whole screen, so it should run before anything else is shown
+pub fn run_benchmarks() -> Vec<BenchmarkResult> {
+    let framebuffer = framebuffer();
+    let black = Color { r: 0, g: 0, b: 0 };
+    let white = Color {
+        r: 255,
+        g: 255,
+        b: 255,
+    };
+    let width = framebuffer.width();
+    let height = framebuffer.height();
+    let mut source = FramebufferColorPixels::new(white, width, height, framebuffer.layout());
+    let mut pixels = FramebufferColorPixels::new(black, width, height, framebuffer.layout());
+
+    let previous_level = simd_level();
+    let mut results = Vec::new();
+    for level in SimdLevel::ALL
+        .into_iter()
+        .filter(|&level| level <= supported_simd_level())
+    {
+        set_simd_level(level);
+
+        let mut result = |name, cycles| {
+            results.push(BenchmarkResult {
+                name,
+                level,
+                cycles,
+            })
+        };
+
+        result("fill", measure(|| pixels.fill(0, 0, width, height, white)));
+        result(
+            "copy",
+            measure(|| pixels.copy_rect_from(&source, Rect::new(0, 0, width, height))),
+        );
+        result(
+            "text",
+            measure(|| {
+                let mut writer = TextWriter {
+                    x: &mut 0,
+                    y: &mut 0,
+                    left_margin: 0,
+                    text_color: white,
+                    background: Some(black),
+                    font: &SPACE_MONO,
+                    screen: &mut source,
+                    previous: None,
+                };
+                for _ in 0..16 {
+                    _ = writeln!(
+                        writer,
+                        "The quick brown fox jumps over the lazy dog 0123456789"
+                    );
+                }
+            }),
+        );
+        result(
+            "present",
+            measure(|| {
+                pixels.mark_all_damaged();
+                framebuffer.present(&mut pixels);
+            }),
+        );
+    }
+    set_simd_level(previous_level);
+
+    results
+}
@@ -0,0 +1,431 @@
+use crate::{
+    ansi::{Parameters, Parser, Sequence, palette_color},
+    framebuffer::Color,
+    screen::Screen,
+    text_writer::TextWriter,
+};
+use alloc::{collections::vec_deque::VecDeque, vec, vec::Vec};
+use core::fmt::Write;
+use font::Font;
+
+const TAB_WIDTH: usize = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cell {
+    pub c: char,
+    pub foreground: Color,
+    pub background: Color,
+    pub bold: bool,
+}
+
+// a grid of characters with scrollback, the bottom `rows` lines are the page that's being written to,
+// and the common ansi escape sequences for colours and moving the cursor around it
+pub struct Console<'a> {
+    font: &'a Font<'a>,
+    // without one, bold text is drawn twice a pixel apart
+    pub bold_font: Option<&'a Font<'a>>,
+    cell_width: usize,
+    cell_height: usize,
+    columns: usize,
+    rows: usize,
+    // oldest first, each one already wrapped to `columns`
+    lines: VecDeque<Vec<Cell>>,
+    max_lines: usize,
+    // an index into `lines`
+    cursor_row: usize,
+    // can be `columns`, the next character then wraps onto the next line first
+    cursor_column: usize,
+    // how many lines back from the bottom the view is
+    scroll: usize,
+    // what the next characters get written with, escape sequences can change them
+    pub foreground: Color,
+    pub background: Color,
+    pub bold: bool,
+    // swaps the foreground and background
+    pub reverse: bool,
+    default_foreground: Color,
+    default_background: Color,
+    parser: Parser,
+    // as a row and column of the page
+    saved_cursor: (usize, usize),
+    // what each cell of the screen was last rendered as, so only changed ones get drawn again
+    displayed: Vec<Option<Cell>>,
+}
+
+impl<'a> Console<'a> {
+    // fills as much of a `width` by `height` screen as whole cells fit in, keeping up to `scrollback` lines above it
+    pub fn new(
+        font: &'a Font<'a>,
+        width: usize,
+        height: usize,
+        scrollback: usize,
+        foreground: Color,
+        background: Color,
+    ) -> Self {
+        // the widest character, so none of them spill into the next cell
+        let cell_width = font
+            .chars
+            .iter()
+            .map(|char| char.xadvance as usize)
+            .max()
+            .unwrap_or(1)
+            .max(1);
+        let cell_height = (font.common.line_height as usize).max(1);
+        let columns = (width / cell_width).max(1);
+        let rows = (height / cell_height).max(1);
+
+        let blank = Cell {
+            c: ' ',
+            foreground,
+            background,
+            bold: false,
+        };
+        Self {
+            font,
+            bold_font: None,
+            cell_width,
+            cell_height,
+            columns,
+            rows,
+            lines: (0..rows).map(|_| vec![blank; columns]).collect(),
+            max_lines: rows.saturating_add(scrollback),
+            cursor_row: 0,
+            cursor_column: 0,
+            scroll: 0,
+            foreground,
+            background,
+            bold: false,
+            reverse: false,
+            default_foreground: foreground,
+            default_background: background,
+            parser: Parser::new(),
+            saved_cursor: (0, 0),
+            displayed: vec![None; columns * rows],
+        }
+    }
+
+    pub fn columns(&self) -> usize {
+        self.columns
+    }
+
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    fn cell(&self, c: char) -> Cell {
+        let (foreground, background) = if self.reverse {
+            (self.background, self.foreground)
+        } else {
+            (self.foreground, self.background)
+        };
+        Cell {
+            c,
+            foreground,
+            background,
+            bold: self.bold,
+        }
+    }
+
+    // erasing fills with the current background
+    fn blank(&self) -> Cell {
+        Cell {
+            bold: false,
+            ..self.cell(' ')
+        }
+    }
+
+    // the index into `lines` of the top of the page
+    fn page_top(&self) -> usize {
+        self.lines.len() - self.rows
+    }
+
+    // as a row and column of the page
+    pub fn cursor(&self) -> (usize, usize) {
+        (self.cursor_row - self.page_top(), self.cursor_column)
+    }
+
+    pub fn set_cursor(&mut self, row: usize, column: usize) {
+        self.cursor_row = self.page_top() + row.min(self.rows - 1);
+        self.cursor_column = column.min(self.columns - 1);
+    }
+
+    // the oldest lines are dropped once there are more than `max_lines`
+    fn new_line(&mut self) {
+        self.cursor_column = 0;
+        self.cursor_row += 1;
+        if self.cursor_row == self.lines.len() {
+            let blank = self.blank();
+            self.lines.push_back(vec![blank; self.columns]);
+            if self.lines.len() > self.max_lines {
+                self.lines.pop_front();
+                self.cursor_row -= 1;
+            }
+        }
+    }
+
+    fn put(&mut self, c: char) {
+        if self.cursor_column == self.columns {
+            self.new_line();
+        }
+        self.lines[self.cursor_row][self.cursor_column] = self.cell(c);
+        self.cursor_column += 1;
+    }
+
+    // wipes everything, including the scrollback
+    pub fn clear(&mut self) {
+        let blank = self.blank();
+        self.lines.clear();
+        self.lines
+            .extend((0..self.rows).map(|_| vec![blank; self.columns]));
+        self.cursor_row = 0;
+        self.cursor_column = 0;
+        self.scroll = 0;
+    }
+
+    pub fn max_scroll(&self) -> usize {
+        self.page_top()
+    }
+
+    // `columns` of `row` in `lines`
+    fn erase(&mut self, row: usize, columns: core::ops::Range<usize>) {
+        let blank = self.blank();
+        self.lines[row][columns].fill(blank);
+    }
+
+    pub fn reset_attributes(&mut self) {
+        self.foreground = self.default_foreground;
+        self.background = self.default_background;
+        self.bold = false;
+        self.reverse = false;
+    }
+
+    pub fn scroll_up(&mut self, lines: usize) {
+        self.scroll = (self.scroll + lines).min(self.max_scroll());
+    }
+
+    pub fn scroll_down(&mut self, lines: usize) {
+        self.scroll = self.scroll.saturating_sub(lines);
+    }
+
+    // a line of overlap is kept, so there's something to follow on from
+    pub fn page_up(&mut self) {
+        self.scroll_up(self.rows.saturating_sub(1).max(1));
+    }
+
+    pub fn page_down(&mut self) {
+        self.scroll_down(self.rows.saturating_sub(1).max(1));
+    }
+
+    // for when something else has drawn over the screen
+    pub fn invalidate(&mut self) {
+        self.displayed.fill(None);
+    }
+
+    // only draws the cells that changed since last time, the screen should be the same one each time
+    pub fn render(&mut self, screen: &mut dyn Screen) {
+        let top = self.lines.len() - self.rows - self.scroll;
+        for (row, line) in self.lines.range(top..top + self.rows).enumerate() {
+            for (column, &cell) in line.iter().enumerate() {
+                let displayed = &mut self.displayed[column + row * self.columns];
+                if *displayed == Some(cell) {
+                    continue;
+                }
+                *displayed = Some(cell);
+
+                let left = column * self.cell_width;
+                let top = row * self.cell_height;
+                screen.fill(
+                    left,
+                    top,
+                    self.cell_width,
+                    self.cell_height,
+                    cell.background,
+                );
+                if cell.c == ' ' {
+                    continue;
+                }
+                let (font, strikes) = match (cell.bold, self.bold_font) {
+                    (true, Some(bold_font)) => (bold_font, 1),
+                    (true, None) => (self.font, 2),
+                    (false, _) => (self.font, 1),
+                };
+                for strike in 0..strikes {
+                    let mut writer = TextWriter {
+                        x: &mut { left + strike },
+                        y: &mut { top },
+                        left_margin: left,
+                        text_color: cell.foreground,
+                        background: None,
+                        font,
+                        screen: &mut *screen,
+                        previous: None,
+                    };
+                    _ = writer.write_char(cell.c);
+                }
+            }
+        }
+    }
+}
+
+impl Console<'_> {
+    fn control(&mut self, c: char) {
+        match c {
+            '\n' => self.new_line(),
+            '\r' => self.cursor_column = 0,
+            '\t' => {
+                let stop = (self.cursor_column / TAB_WIDTH + 1) * TAB_WIDTH;
+                while self.cursor_column < stop.min(self.columns) {
+                    self.put(' ');
+                }
+            }
+            '\x08' => self.cursor_column = self.cursor_column.saturating_sub(1),
+            _ => {}
+        }
+    }
+
+    fn escape(&mut self, c: char) {
+        match c {
+            '7' => self.saved_cursor = self.cursor(),
+            '8' => self.set_cursor(self.saved_cursor.0, self.saved_cursor.1),
+            // a full reset
+            'c' => {
+                self.reset_attributes();
+                self.clear();
+            }
+            _ => {}
+        }
+    }
+
+    fn csi(&mut self, parameters: &Parameters, action: char) {
+        let (row, column) = self.cursor();
+        let count = parameters.get_or(0, 1) as usize;
+        match action {
+            'A' => self.set_cursor(row.saturating_sub(count), column),
+            'B' => self.set_cursor(row + count, column),
+            'C' => self.set_cursor(row, column + count),
+            'D' => self.set_cursor(row, column.saturating_sub(count)),
+            'E' => self.set_cursor(row + count, 0),
+            'F' => self.set_cursor(row.saturating_sub(count), 0),
+            'G' => self.set_cursor(row, count - 1),
+            'd' => self.set_cursor(count - 1, column),
+            // rows and columns count from 1
+            'H' | 'f' => self.set_cursor(
+                parameters.get_or(0, 1) as usize - 1,
+                parameters.get_or(1, 1) as usize - 1,
+            ),
+            'J' => {
+                let top = self.page_top();
+                let rows = match parameters.get_or(0, 0) {
+                    0 => {
+                        self.erase(self.cursor_row, column.min(self.columns)..self.columns);
+                        self.cursor_row + 1..self.lines.len()
+                    }
+                    1 => {
+                        self.erase(self.cursor_row, 0..(column + 1).min(self.columns));
+                        top..self.cursor_row
+                    }
+                    2 => top..self.lines.len(),
+                    // the scrollback, leaving the page as it is
+                    3 => {
+                        self.lines.drain(..top);
+                        self.cursor_row -= top;
+                        self.scroll = 0;
+                        0..0
+                    }
+                    _ => 0..0,
+                };
+                for row in rows {
+                    self.erase(row, 0..self.columns);
+                }
+            }
+            'K' => {
+                let columns = match parameters.get_or(0, 0) {
+                    0 => column.min(self.columns)..self.columns,
+                    1 => 0..(column + 1).min(self.columns),
+                    2 => 0..self.columns,
+                    _ => 0..0,
+                };
+                self.erase(self.cursor_row, columns);
+            }
+            'm' => self.select_graphic_rendition(parameters.as_slice()),
+            's' => self.saved_cursor = (row, column),
+            'u' => self.set_cursor(self.saved_cursor.0, self.saved_cursor.1),
+            _ => {}
+        }
+    }
+
+    // `38;5;n` and `38;2;r;g;b` and the same with 48 for the background, returns how many parameters it used
+    fn extended_color(parameters: &[u16]) -> (Option<Color>, usize) {
+        match parameters {
+            [5, index, ..] => (Some(palette_color(*index as u8)), 2),
+            [2, r, g, b, ..] => (
+                Some(Color {
+                    r: *r as u8,
+                    g: *g as u8,
+                    b: *b as u8,
+                }),
+                4,
+            ),
+            _ => (None, parameters.len()),
+        }
+    }
+
+    fn select_graphic_rendition(&mut self, parameters: &[u16]) {
+        // no parameters is the same as a reset
+        if parameters.is_empty() {
+            self.reset_attributes();
+        }
+        let mut index = 0;
+        while index < parameters.len() {
+            let parameter = parameters[index];
+            index += 1;
+            match parameter {
+                0 => self.reset_attributes(),
+                1 => self.bold = true,
+                22 => self.bold = false,
+                7 => self.reverse = true,
+                27 => self.reverse = false,
+                30..=37 => self.foreground = palette_color((parameter - 30) as u8),
+                90..=97 => self.foreground = palette_color((parameter - 90 + 8) as u8),
+                39 => self.foreground = self.default_foreground,
+                40..=47 => self.background = palette_color((parameter - 40) as u8),
+                100..=107 => self.background = palette_color((parameter - 100 + 8) as u8),
+                49 => self.background = self.default_background,
+                38 | 48 => {
+                    let (color, used) = Self::extended_color(&parameters[index..]);
+                    index += used;
+                    match (parameter, color) {
+                        (38, Some(color)) => self.foreground = color,
+                        (_, Some(color)) => self.background = color,
+                        _ => {}
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+impl Write for Console<'_> {
+    // new output always scrolls back down to the bottom
+    fn write_char(&mut self, c: char) -> core::fmt::Result {
+        self.scroll = 0;
+        match self.parser.advance(c) {
+            Some(Sequence::Print(c)) => self.put(c),
+            Some(Sequence::Control(c)) => self.control(c),
+            Some(Sequence::Escape(c)) => self.escape(c),
+            // private sequences like showing and hiding the cursor don't mean anything here
+            Some(Sequence::Csi {
+                parameters,
+                private: None,
+                action,
+            }) => self.csi(&parameters, action),
+            Some(Sequence::Csi { .. }) | None => {}
+        }
+        Ok(())
+    }
+
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        s.chars().try_for_each(|c| self.write_char(c))
+    }
+}
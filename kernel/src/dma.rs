@@ -0,0 +1,65 @@
+use crate::page_allocator::{PAGE_ALLOCATOR, PAGE_SIZE, Zone};
+use core::{
+    alloc::Layout,
+    ops::{Deref, DerefMut},
+};
+
+pub struct DmaBuffer {
+    address: usize,
+    layout: Layout,
+}
+
+impl DmaBuffer {
+    // the returned memory is physically contiguous and zeroed
+    pub fn new(size: usize, align: usize, zone: Zone) -> Option<Self> {
+        let layout = Layout::from_size_align(size, align.max(PAGE_SIZE)).ok()?;
+        let address = PAGE_ALLOCATOR.with(|alloc| alloc.allocate_in_zone(layout, zone))?;
+        unsafe {
+            core::ptr::write_bytes(
+                core::ptr::with_exposed_provenance_mut::<u8>(address),
+                0,
+                size,
+            )
+        };
+        Some(Self { address, layout })
+    }
+
+    // all physical memory is identity mapped, so this is also the virtual address
+    pub fn physical_address(&self) -> usize {
+        self.address
+    }
+
+    pub fn zone(&self) -> Zone {
+        Zone::of(self.address + self.layout.size() - 1)
+    }
+}
+
+impl Deref for DmaBuffer {
+    type Target = [u8];
+
+    fn deref(&self) -> &Self::Target {
+        unsafe {
+            core::slice::from_raw_parts(
+                core::ptr::with_exposed_provenance(self.address),
+                self.layout.size(),
+            )
+        }
+    }
+}
+
+impl DerefMut for DmaBuffer {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        unsafe {
+            core::slice::from_raw_parts_mut(
+                core::ptr::with_exposed_provenance_mut(self.address),
+                self.layout.size(),
+            )
+        }
+    }
+}
+
+impl Drop for DmaBuffer {
+    fn drop(&mut self) {
+        PAGE_ALLOCATOR.with(|alloc| unsafe { alloc.free(self.address, self.layout) });
+    }
+}
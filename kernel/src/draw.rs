@@ -0,0 +1,462 @@
+use crate::{framebuffer::Color, screen::Screen};
+use alloc::vec::Vec;
+
+// screen coordinates, signed so shapes can hang off any edge and get clipped
+pub type Point = (isize, isize);
+
+// polygons and thick lines clamp their points to this, and rounded rects their sizes, so the edge maths cant overflow
+const MAX_COORDINATE: isize = 1 << 40;
+
+// far bigger than any screen, but small enough that the products of squared radii below fit in 128 bits
+const MAX_RADIUS: usize = 1 << 31;
+
+fn clamp_size(width: usize, height: usize) -> (usize, usize) {
+    (
+        width.min(MAX_COORDINATE as usize),
+        height.min(MAX_COORDINATE as usize),
+    )
+}
+
+fn clamp_point((x, y): Point) -> Point {
+    (
+        x.clamp(-MAX_COORDINATE, MAX_COORDINATE),
+        y.clamp(-MAX_COORDINATE, MAX_COORDINATE),
+    )
+}
+
+// shapes on top of any `Screen`, everything is clipped against the screen bounds
+pub trait Draw: Screen {
+    fn plot(&mut self, x: isize, y: isize, color: Color) {
+        if x >= 0 && y >= 0 {
+            self.set_pixel(x as usize, y as usize, color);
+        }
+    }
+
+    // mixes `color` into whatever is already there, used for anti-aliasing
+    fn plot_blended(&mut self, x: isize, y: isize, color: Color, alpha: u8) {
+        if x >= 0
+            && y >= 0
+            && let Some(existing) = self.get_pixel(x as usize, y as usize)
+        {
+            self.set_pixel(x as usize, y as usize, existing.lerp(color, alpha));
+        }
+    }
+
+    // `right` is exclusive
+    fn fill_span(&mut self, left: isize, right: isize, y: isize, color: Color) {
+        let left = left.max(0);
+        if y >= 0 && right > left {
+            self.fill(left as usize, y as usize, (right - left) as usize, 1, color);
+        }
+    }
+
+    fn fill_rect(&mut self, left: isize, top: isize, width: usize, height: usize, color: Color) {
+        let (left, width) = clip_start(left, width);
+        let (top, height) = clip_start(top, height);
+        self.fill(left, top, width, height, color);
+    }
+
+    fn draw_rect(&mut self, left: isize, top: isize, width: usize, height: usize, color: Color) {
+        if width == 0 || height == 0 {
+            return;
+        }
+        let right = left.saturating_add_unsigned(width - 1);
+        let bottom = top.saturating_add_unsigned(height - 1);
+        self.fill_rect(left, top, width, 1, color);
+        self.fill_rect(left, bottom, width, 1, color);
+        self.fill_rect(left, top, 1, height, color);
+        self.fill_rect(right, top, 1, height, color);
+    }
+
+    fn draw_line(&mut self, from: Point, to: Point, color: Color) {
+        let Some(((mut x, mut y), (x1, y1))) = clip_line(from, to, self.width(), self.height())
+        else {
+            return;
+        };
+
+        let dx = (x1 - x).abs();
+        let dy = -(y1 - y).abs();
+        let step_x = if x < x1 { 1 } else { -1 };
+        let step_y = if y < y1 { 1 } else { -1 };
+        let mut error = dx + dy;
+        loop {
+            self.plot(x, y, color);
+            if x == x1 && y == y1 {
+                break;
+            }
+            let doubled = 2 * error;
+            if doubled >= dy {
+                error += dy;
+                x += step_x;
+            }
+            if doubled <= dx {
+                error += dx;
+                y += step_y;
+            }
+        }
+    }
+
+    // xiaolin wu's line, in 16.16 fixed point since the kernel has no floats
+    fn draw_line_antialiased(&mut self, from: Point, to: Point, color: Color) {
+        let ((mut x0, mut y0), (mut x1, mut y1)) = (from, to);
+        let steep = y1.abs_diff(y0) > x1.abs_diff(x0);
+        if steep {
+            (x0, y0, x1, y1) = (y0, x0, y1, x1);
+        }
+        if x0 > x1 {
+            (x0, y0, x1, y1) = (x1, y1, x0, y0);
+        }
+
+        let dx = x1 as i128 - x0 as i128;
+        let dy = y1 as i128 - y0 as i128;
+        let gradient = if dx == 0 { 0 } else { (dy << 16) / dx };
+        let major_length = if steep { self.height() } else { self.width() } as isize;
+
+        // only walk the part of the major axis that is on screen
+        for x in x0.max(0)..=x1.min(major_length - 1) {
+            let y = ((y0 as i128) << 16) + gradient * (x as i128 - x0 as i128);
+            let whole = (y >> 16).clamp(isize::MIN as i128, isize::MAX as i128 - 1) as isize;
+            let fraction = ((y & 0xFFFF) >> 8) as u8;
+            if steep {
+                self.plot_blended(whole, x, color, u8::MAX - fraction);
+                self.plot_blended(whole + 1, x, color, fraction);
+            } else {
+                self.plot_blended(x, whole, color, u8::MAX - fraction);
+                self.plot_blended(x, whole + 1, color, fraction);
+            }
+        }
+    }
+
+    // butt ends, a `thickness` of 1 or less is a plain line
+    fn draw_thick_line(&mut self, from: Point, to: Point, thickness: usize, color: Color) {
+        if thickness <= 1 {
+            self.draw_line(from, to, color);
+            return;
+        }
+
+        let (from, to) = (clamp_point(from), clamp_point(to));
+        let dx = to.0 as i128 - from.0 as i128;
+        let dy = to.1 as i128 - from.1 as i128;
+        let length = ((dx * dx + dy * dy) as u128).isqrt() as i128;
+        if length == 0 {
+            self.fill_circle(from, thickness / 2, color);
+            return;
+        }
+
+        // half the thickness along the normal, rounded to the nearest pixel
+        let thickness = thickness as i128;
+        let offset_x = round_div(-dy * thickness, 2 * length) as isize;
+        let offset_y = round_div(dx * thickness, 2 * length) as isize;
+        self.fill_polygon(
+            &[
+                (from.0 + offset_x, from.1 + offset_y),
+                (to.0 + offset_x, to.1 + offset_y),
+                (to.0 - offset_x, to.1 - offset_y),
+                (from.0 - offset_x, from.1 - offset_y),
+            ],
+            color,
+        );
+    }
+
+    // connected thick lines with round joins
+    fn draw_polyline(&mut self, points: &[Point], thickness: usize, color: Color) {
+        for segment in points.windows(2) {
+            self.draw_thick_line(segment[0], segment[1], thickness, color);
+        }
+        if thickness > 1 && points.len() > 2 {
+            for &joint in &points[1..points.len() - 1] {
+                self.fill_circle(joint, thickness / 2, color);
+            }
+        }
+    }
+
+    // the outline of a closed polygon
+    fn draw_polygon(&mut self, points: &[Point], color: Color) {
+        for (index, &from) in points.iter().enumerate() {
+            self.draw_line(from, points[(index + 1) % points.len()], color);
+        }
+    }
+
+    // even-odd fill, sampling at the centre of every pixel
+    fn fill_polygon(&mut self, points: &[Point], color: Color) {
+        if points.len() < 3 {
+            return;
+        }
+
+        let top = points.iter().map(|point| point.1).min().unwrap().max(0);
+        let bottom = points
+            .iter()
+            .map(|point| clamp_point(*point).1)
+            .max()
+            .unwrap()
+            .min(self.height() as isize - 1);
+        let width = self.width() as i128;
+
+        let mut crossings = Vec::new();
+        for y in top..=bottom {
+            // everything is doubled so the pixel centre stays an integer
+            let sample = 2 * y as i128 + 1;
+            crossings.clear();
+            for (index, &point) in points.iter().enumerate() {
+                let (x0, y0) = clamp_point(point);
+                let (x1, y1) = clamp_point(points[(index + 1) % points.len()]);
+                let (y0, y1) = (2 * y0 as i128, 2 * y1 as i128);
+                if (y0 <= sample) != (y1 <= sample) {
+                    let x =
+                        2 * x0 as i128 + (sample - y0) * 2 * (x1 as i128 - x0 as i128) / (y1 - y0);
+                    crossings.push(x.clamp(-2, 2 * width + 2));
+                }
+            }
+            crossings.sort_unstable();
+
+            // pixel x is inside when its centre 2x + 1 is between a pair of crossings
+            for pair in crossings.chunks_exact(2) {
+                let left = pair[0].div_euclid(2) as isize;
+                let right = pair[1].div_euclid(2) as isize;
+                self.fill_span(left, right, y, color);
+            }
+        }
+    }
+
+    fn draw_ellipse(&mut self, center: Point, radius_x: usize, radius_y: usize, color: Color) {
+        let radius_x = radius_x.min(MAX_RADIUS);
+        let radius_y = radius_y.min(MAX_RADIUS);
+        if !ellipse_on_screen(center, radius_x, radius_y, self.width(), self.height()) {
+            return;
+        }
+
+        // a row at a time over just the rows that are on screen, so huge ellipses cost no more than small ones
+        // each row goes from the edge of the ellipse in to just past where the next row out reaches,
+        // which keeps the outline joined up where it is nearly flat
+        let (center_x, center_y) = center;
+        let a = radius_x as u128;
+        let b = radius_y as u128;
+        let top = center_y.saturating_sub_unsigned(radius_y).max(0);
+        let bottom = center_y
+            .saturating_add_unsigned(radius_y)
+            .min(self.height() as isize - 1);
+        for y in top..=bottom {
+            let dy = (y - center_y).unsigned_abs() as u128;
+            let Some(outer) = ellipse_half_width(a, b, dy) else {
+                continue;
+            };
+            // the rows at the very top and bottom are drawn all the way across
+            let inner = ellipse_half_width(a, b, dy + 1).map_or(0, |next| (next + 1).min(outer));
+            let (outer, inner) = (outer as isize, inner as isize);
+            self.fill_span(center_x - outer, center_x - inner + 1, y, color);
+            self.fill_span(center_x + inner, center_x + outer + 1, y, color);
+        }
+    }
+
+    fn fill_ellipse(&mut self, center: Point, radius_x: usize, radius_y: usize, color: Color) {
+        let radius_x = radius_x.min(MAX_RADIUS);
+        let radius_y = radius_y.min(MAX_RADIUS);
+        if !ellipse_on_screen(center, radius_x, radius_y, self.width(), self.height()) {
+            return;
+        }
+
+        let (center_x, center_y) = center;
+        let a = radius_x as u128;
+        let b = radius_y as u128;
+        let top = center_y.saturating_sub_unsigned(radius_y).max(0);
+        let bottom = center_y
+            .saturating_add_unsigned(radius_y)
+            .min(self.height() as isize - 1);
+        for y in top..=bottom {
+            let dy = (y - center_y).unsigned_abs() as u128;
+            if let Some(half_width) = ellipse_half_width(a, b, dy) {
+                let half_width = half_width as isize;
+                self.fill_span(center_x - half_width, center_x + half_width + 1, y, color);
+            }
+        }
+    }
+
+    fn draw_circle(&mut self, center: Point, radius: usize, color: Color) {
+        self.draw_ellipse(center, radius, radius, color);
+    }
+
+    fn fill_circle(&mut self, center: Point, radius: usize, color: Color) {
+        self.fill_ellipse(center, radius, radius, color);
+    }
+
+    fn fill_rounded_rect(
+        &mut self,
+        left: isize,
+        top: isize,
+        width: usize,
+        height: usize,
+        radius: usize,
+        color: Color,
+    ) {
+        let (width, height) = clamp_size(width, height);
+        let radius = radius.min(width / 2).min(height / 2);
+        for row in visible_rows(top, height, self.height()) {
+            let inset = rounded_inset(radius, row, height) as isize;
+            let y = top + row as isize;
+            let right = left.saturating_add_unsigned(width);
+            self.fill_span(left.saturating_add(inset), right - inset, y, color);
+        }
+    }
+
+    fn draw_rounded_rect(
+        &mut self,
+        left: isize,
+        top: isize,
+        width: usize,
+        height: usize,
+        radius: usize,
+        color: Color,
+    ) {
+        let (width, height) = clamp_size(width, height);
+        let radius = radius.min(width / 2).min(height / 2);
+        let width = width as isize;
+        for row in visible_rows(top, height, self.height()) {
+            let inset = rounded_inset(radius, row, height) as isize;
+            // rows outside the rect count as fully inset, so the top and bottom rows are drawn whole
+            let neighbour_inset = |row: Option<usize>| {
+                row.filter(|&row| row < height)
+                    .map_or(width, |row| rounded_inset(radius, row, height) as isize)
+            };
+            let edge = (inset + 1)
+                .max(neighbour_inset(row.checked_sub(1)))
+                .max(neighbour_inset(Some(row + 1)))
+                .min(width - inset);
+
+            let y = top + row as isize;
+            let right = left.saturating_add(width);
+            self.fill_span(
+                left.saturating_add(inset),
+                left.saturating_add(edge),
+                y,
+                color,
+            );
+            self.fill_span(right - edge, right - inset, y, color);
+        }
+    }
+}
+
+impl<T: Screen + ?Sized> Draw for T {}
+
+// moves a start that is off the top or left edge onto it, shortening the length to match
+fn clip_start(start: isize, length: usize) -> (usize, usize) {
+    if start >= 0 {
+        (start as usize, length)
+    } else {
+        (0, length.saturating_sub(start.unsigned_abs()))
+    }
+}
+
+// rounds halves away from zero, `denominator` has to be positive
+fn round_div(numerator: i128, denominator: i128) -> i128 {
+    if numerator >= 0 {
+        (numerator + denominator / 2) / denominator
+    } else {
+        (numerator - denominator / 2) / denominator
+    }
+}
+
+// the rows of a shape that can be on screen, relative to its top
+fn visible_rows(top: isize, height: usize, screen_height: usize) -> core::ops::Range<usize> {
+    let first = top.min(0).unsigned_abs().min(height);
+    let last = (screen_height as isize)
+        .saturating_sub(top)
+        .clamp(0, height as isize) as usize;
+    first..last.max(first)
+}
+
+// how far in from each side a row of a rounded rect starts, measured at the centre of the row
+fn rounded_inset(radius: usize, row: usize, height: usize) -> usize {
+    let from_edge = row.min(height - 1 - row);
+    if from_edge >= radius {
+        return 0;
+    }
+    // doubled so the centre of the row is an integer, and in 128 bits since the radius can be up to `MAX_COORDINATE`
+    let radius = radius as u128;
+    let dy = 2 * (radius - from_edge as u128) - 1;
+    (radius - (4 * radius * radius - dy * dy).isqrt() / 2) as usize
+}
+
+// how far either side of the centre the ellipse reaches `dy` rows from it, `None` past the top and bottom
+fn ellipse_half_width(radius_x: u128, radius_y: u128, dy: u128) -> Option<u128> {
+    (dy <= radius_y).then(|| {
+        // a flat ellipse is just a line
+        (radius_x * radius_x * (radius_y * radius_y - dy * dy))
+            .isqrt()
+            .checked_div(radius_y)
+            .unwrap_or(radius_x)
+    })
+}
+
+fn ellipse_on_screen(
+    (x, y): Point,
+    radius_x: usize,
+    radius_y: usize,
+    width: usize,
+    height: usize,
+) -> bool {
+    x.saturating_add_unsigned(radius_x) >= 0
+        && y.saturating_add_unsigned(radius_y) >= 0
+        && x.saturating_sub_unsigned(radius_x) < width as isize
+        && y.saturating_sub_unsigned(radius_y) < height as isize
+}
+
+// cohen-sutherland, so lines that are mostly off screen dont get walked pixel by pixel
+fn clip_line(from: Point, to: Point, width: usize, height: usize) -> Option<(Point, Point)> {
+    const LEFT: u8 = 1;
+    const RIGHT: u8 = 2;
+    const TOP: u8 = 4;
+    const BOTTOM: u8 = 8;
+
+    if width == 0 || height == 0 {
+        return None;
+    }
+    let max_x = width as i128 - 1;
+    let max_y = height as i128 - 1;
+    let outcode = |(x, y): (i128, i128)| {
+        let mut code = 0;
+        if x < 0 {
+            code |= LEFT;
+        } else if x > max_x {
+            code |= RIGHT;
+        }
+        if y < 0 {
+            code |= TOP;
+        } else if y > max_y {
+            code |= BOTTOM;
+        }
+        code
+    };
+
+    let mut from = (from.0 as i128, from.1 as i128);
+    let mut to = (to.0 as i128, to.1 as i128);
+    loop {
+        let from_code = outcode(from);
+        let to_code = outcode(to);
+        if from_code | to_code == 0 {
+            return Some((
+                (from.0 as isize, from.1 as isize),
+                (to.0 as isize, to.1 as isize),
+            ));
+        }
+        if from_code & to_code != 0 {
+            return None;
+        }
+
+        let code = if from_code != 0 { from_code } else { to_code };
+        let ((x0, y0), (x1, y1)) = (from, to);
+        let clipped = if code & TOP != 0 {
+            (x0 + (x1 - x0) * (0 - y0) / (y1 - y0), 0)
+        } else if code & BOTTOM != 0 {
+            (x0 + (x1 - x0) * (max_y - y0) / (y1 - y0), max_y)
+        } else if code & LEFT != 0 {
+            (0, y0 + (y1 - y0) * (0 - x0) / (x1 - x0))
+        } else {
+            (max_x, y0 + (y1 - y0) * (max_x - x0) / (x1 - x0))
+        };
+        if code == from_code {
+            from = clipped;
+        } else {
+            to = clipped;
+        }
+    }
+}
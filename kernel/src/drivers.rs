@@ -1,3 +1,4 @@
 pub mod pic;
 pub mod ps2_keyboard;
 pub mod ps2_mouse;
+pub mod serial;
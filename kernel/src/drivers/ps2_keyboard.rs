@@ -106,6 +106,7 @@ pub enum Key {
     VolumeUp,
     WWWHome,
     KeypadSlash,
+    PrintScreen,
     RightAlt,
     Home,
     CursorUp,
@@ -310,6 +311,8 @@ pub unsafe extern "x86-interrupt" fn keyboard_handler(_: InterruptStackFrame) {
                             0x30 => Key::VolumeUp,
                             0x32 => Key::WWWHome,
                             0x35 => Key::KeypadSlash,
+                            // after a fake left shift of 0xE0 0x2A, which comes through as unknown
+                            0x37 => Key::PrintScreen,
                             0x38 => Key::RightAlt,
                             0x47 => Key::Home,
                             0x48 => Key::CursorUp,
@@ -5,6 +5,7 @@ use crate::{
     utils::{inb, io_wait, outb},
 };
 use alloc::collections::vec_deque::VecDeque;
+use core::str::FromStr;
 use enum_map::{Enum, EnumMap, enum_map};
 
 pub fn mouse_wait() {
@@ -83,6 +84,25 @@ pub struct MouseEvent {
     pub y_offset: i16,
 }
 
+// how many pixels the cursor moves for each unit the mouse reports, set with `mouse.sensitivity=`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MouseSensitivity(pub isize);
+
+impl MouseSensitivity {
+    pub const MAX: isize = 64;
+}
+
+impl FromStr for MouseSensitivity {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.parse() {
+            Ok(sensitivity @ 1..=Self::MAX) => Ok(Self(sensitivity)),
+            _ => Err(()),
+        }
+    }
+}
+
 pub struct MouseState {
     mouse_events: VecDeque<MouseEvent>,
     data_state: MouseDataState,
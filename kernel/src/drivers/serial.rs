@@ -0,0 +1,49 @@
+use crate::utils::{inb, outb};
+use core::fmt;
+
+const COM1: u16 = 0x3F8;
+const DATA: u16 = COM1;
+const INTERRUPT_ENABLE: u16 = COM1 + 1;
+// while the divisor latch is set, the first two registers are the baud rate divisor instead
+const DIVISOR_LOW: u16 = COM1;
+const DIVISOR_HIGH: u16 = COM1 + 1;
+const FIFO_CONTROL: u16 = COM1 + 2;
+const LINE_CONTROL: u16 = COM1 + 3;
+const MODEM_CONTROL: u16 = COM1 + 4;
+const LINE_STATUS: u16 = COM1 + 5;
+
+const LINE_CONTROL_DIVISOR_LATCH: u8 = 0x80;
+const LINE_CONTROL_8N1: u8 = 0x03;
+const LINE_STATUS_TRANSMIT_EMPTY: u8 = 0x20;
+
+// 115200 baud with 8 data bits, no parity and 1 stop bit, and no interrupts since it's only written to
+pub unsafe fn setup_serial() {
+    unsafe { outb::<INTERRUPT_ENABLE>(0) };
+    unsafe { outb::<LINE_CONTROL>(LINE_CONTROL_DIVISOR_LATCH) };
+    unsafe { outb::<DIVISOR_LOW>(1) };
+    unsafe { outb::<DIVISOR_HIGH>(0) };
+    unsafe { outb::<LINE_CONTROL>(LINE_CONTROL_8N1) };
+    // enable and clear the fifos
+    unsafe { outb::<FIFO_CONTROL>(0xC7) };
+    // data terminal ready and request to send
+    unsafe { outb::<MODEM_CONTROL>(0x03) };
+}
+
+// without a serial port the line status reads as all ones, so this doesn't wait forever
+pub fn write_serial(bytes: &[u8]) {
+    for &byte in bytes {
+        while unsafe { inb::<LINE_STATUS>() } & LINE_STATUS_TRANSMIT_EMPTY == 0 {
+            core::hint::spin_loop();
+        }
+        unsafe { outb::<DATA>(byte) };
+    }
+}
+
+pub struct SerialWriter;
+
+impl fmt::Write for SerialWriter {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        write_serial(s.as_bytes());
+        Ok(())
+    }
+}
@@ -0,0 +1,549 @@
+use crate::{
+    screen::{FramebufferColorPixels, Screen},
+    simd::{copy_row, fill_row},
+};
+use boot_info::{FramebufferInfo, PixelFormat};
+use core::{arch::asm, cell::SyncUnsafeCell, str::FromStr};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(C)]
+pub struct Color {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+}
+
+impl Color {
+    pub const fn add(self, other: Self) -> Self {
+        Self {
+            r: self.r.saturating_add(other.r),
+            g: self.g.saturating_add(other.g),
+            b: self.b.saturating_add(other.b),
+        }
+    }
+
+    pub const fn lerp(self, other: Self, t: u8) -> Self {
+        self.scale(u8::MAX - t).add(other.scale(t))
+    }
+
+    pub const fn multiply(self, other: Self) -> Self {
+        Self {
+            r: ((self.r as u16 * other.r as u16) / u8::MAX as u16) as u8,
+            g: ((self.g as u16 * other.g as u16) / u8::MAX as u16) as u8,
+            b: ((self.b as u16 * other.b as u16) / u8::MAX as u16) as u8,
+        }
+    }
+
+    pub const fn scale(self, brightness: u8) -> Self {
+        self.multiply(Color {
+            r: brightness,
+            g: brightness,
+            b: brightness,
+        })
+    }
+}
+
+// premultiplied, so r, g and b are never more than a
+// the fields are private so that always holds, build one with `new` or `opaque`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(C)]
+pub struct Rgba {
+    r: u8,
+    g: u8,
+    b: u8,
+    a: u8,
+}
+
+// porter-duff operators for putting a source on a destination
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlendMode {
+    // only the source, the destination is replaced
+    Source,
+    // the source on top of the destination
+    Over,
+    // the source, only where the destination is
+    In,
+    // the source, only where the destination isnt
+    Out,
+    // the source on top of the destination, only where the destination is
+    Atop,
+    // each of them only where the other isnt
+    Xor,
+}
+
+const fn multiply_channel(x: u8, y: u8) -> u8 {
+    ((x as u16 * y as u16) / u8::MAX as u16) as u8
+}
+
+impl Rgba {
+    pub const TRANSPARENT: Self = Self {
+        r: 0,
+        g: 0,
+        b: 0,
+        a: 0,
+    };
+
+    pub const fn new(color: Color, alpha: u8) -> Self {
+        Self::opaque(color).scale(alpha)
+    }
+
+    pub const fn opaque(color: Color) -> Self {
+        Self {
+            r: color.r,
+            g: color.g,
+            b: color.b,
+            a: u8::MAX,
+        }
+    }
+
+    // undoes the premultiplication, fully transparent comes out black
+    pub fn color(self) -> Color {
+        if self.a == 0 {
+            return Color { r: 0, g: 0, b: 0 };
+        }
+        let unpremultiply = |value: u8| ((value as u16 * u8::MAX as u16) / self.a as u16) as u8;
+        Color {
+            r: unpremultiply(self.r),
+            g: unpremultiply(self.g),
+            b: unpremultiply(self.b),
+        }
+    }
+
+    // fades out all 4 channels, which keeps it premultiplied
+    pub const fn scale(self, alpha: u8) -> Self {
+        Self {
+            r: multiply_channel(self.r, alpha),
+            g: multiply_channel(self.g, alpha),
+            b: multiply_channel(self.b, alpha),
+            a: multiply_channel(self.a, alpha),
+        }
+    }
+
+    pub const fn add(self, other: Self) -> Self {
+        Self {
+            r: self.r.saturating_add(other.r),
+            g: self.g.saturating_add(other.g),
+            b: self.b.saturating_add(other.b),
+            a: self.a.saturating_add(other.a),
+        }
+    }
+
+    // with `self` as the source
+    pub const fn blend(self, destination: Self, mode: BlendMode) -> Self {
+        let (source_factor, destination_factor) = match mode {
+            BlendMode::Source => (u8::MAX, 0),
+            BlendMode::Over => (u8::MAX, u8::MAX - self.a),
+            BlendMode::In => (destination.a, 0),
+            BlendMode::Out => (u8::MAX - destination.a, 0),
+            BlendMode::Atop => (destination.a, u8::MAX - self.a),
+            BlendMode::Xor => (u8::MAX - destination.a, u8::MAX - self.a),
+        };
+        self.scale(source_factor)
+            .add(destination.scale(destination_factor))
+    }
+
+    // onto something opaque, which stays opaque
+    pub fn over(self, destination: Color) -> Color {
+        self.blend(Self::opaque(destination), BlendMode::Over)
+            .color()
+    }
+}
+
+impl From<Color> for Rgba {
+    fn from(color: Color) -> Self {
+        Self::opaque(color)
+    }
+}
+
+// either `r,g,b` in decimal or `#rrggbb`
+impl FromStr for Color {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(hex) = s.strip_prefix('#') {
+            if hex.len() != 6 {
+                return Err(());
+            }
+            let [_, r, g, b] = u32::from_str_radix(hex, 16).map_err(|_| ())?.to_be_bytes();
+            return Ok(Self { r, g, b });
+        }
+
+        let mut components = s.split(',').map(|component| component.trim().parse::<u8>());
+        match (
+            components.next(),
+            components.next(),
+            components.next(),
+            components.next(),
+        ) {
+            (Some(Ok(r)), Some(Ok(g)), Some(Ok(b)), None) => Ok(Self { r, g, b }),
+            _ => Err(()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Channel {
+    shift: u32,
+    width: u32,
+}
+
+impl Channel {
+    const fn new(mask: u32) -> Self {
+        let shift = mask.trailing_zeros() % 32;
+        Self {
+            shift,
+            width: (mask >> shift).trailing_ones(),
+        }
+    }
+
+    const fn max(self) -> u32 {
+        if self.width == 0 {
+            0
+        } else {
+            u32::MAX >> (32 - self.width)
+        }
+    }
+
+    const fn is_byte(self) -> bool {
+        self.width == 8 && self.shift.is_multiple_of(8)
+    }
+
+    const fn encode(self, value: u8) -> u32 {
+        let value = value as u32;
+        let value = if self.width >= 8 {
+            value << (self.width - 8)
+        } else {
+            value >> (8 - self.width)
+        };
+        value << self.shift
+    }
+
+    const fn decode(self, pixel: u32) -> u8 {
+        let value = (pixel >> self.shift) & self.max();
+        if self.width >= 8 {
+            (value >> (self.width - 8)) as u8
+        } else if self.width == 0 {
+            0
+        } else {
+            (value * u8::MAX as u32 / self.max()) as u8
+        }
+    }
+}
+
+// where each channel is in a pixel, worked out from the masks once instead of for every pixel
+// every buffer of `FramebufferColor`s carries the layout its pixels are in
+// buffers in memory always use 32 bits per pixel, only the framebuffer itself packs them into `bytes_per_pixel`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PixelLayout {
+    red: Channel,
+    green: Channel,
+    blue: Channel,
+    bytes_per_pixel: usize,
+}
+
+impl PixelLayout {
+    pub const fn new(format: PixelFormat) -> Self {
+        let [red, green, blue] = format.masks();
+        Self {
+            red: Channel::new(red),
+            green: Channel::new(green),
+            blue: Channel::new(blue),
+            bytes_per_pixel: format.bytes_per_pixel(),
+        }
+    }
+
+    pub const fn bytes_per_pixel(self) -> usize {
+        self.bytes_per_pixel
+    }
+
+    pub const fn encode(self, color: Color) -> FramebufferColor {
+        FramebufferColor(
+            self.red.encode(color.r) | self.green.encode(color.g) | self.blue.encode(color.b),
+        )
+    }
+
+    pub const fn decode(self, color: FramebufferColor) -> Color {
+        Color {
+            r: self.red.decode(color.0),
+            g: self.green.decode(color.0),
+            b: self.blue.decode(color.0),
+        }
+    }
+
+    // 8 bit channels that each sit in their own byte, which the simd blending relies on
+    pub const fn is_byte_aligned(self) -> bool {
+        self.red.is_byte() && self.green.is_byte() && self.blue.is_byte()
+    }
+
+    pub fn encode_slice(self, colors: &[Color], out: &mut [FramebufferColor]) {
+        assert_eq!(colors.len(), out.len());
+        for (color, out) in colors.iter().zip(out) {
+            *out = self.encode(*color);
+        }
+    }
+
+    pub fn decode_slice(self, colors: &[FramebufferColor], out: &mut [Color]) {
+        assert_eq!(colors.len(), out.len());
+        for (color, out) in colors.iter().zip(out) {
+            *out = self.decode(*color);
+        }
+    }
+}
+
+pub struct Framebuffer {
+    layout: PixelLayout,
+    pixels_base: *mut u8,
+    pixels_width: usize,
+    pixels_height: usize,
+    pixels_per_scanline: usize,
+}
+
+unsafe impl Send for Framebuffer {}
+unsafe impl Sync for Framebuffer {}
+
+impl Framebuffer {
+    pub fn base(&self) -> usize {
+        self.pixels_base.addr()
+    }
+
+    // in bytes
+    pub fn size(&self) -> usize {
+        self.pixels_height * self.pitch()
+    }
+
+    fn pitch(&self) -> usize {
+        self.pixels_per_scanline * self.layout.bytes_per_pixel
+    }
+
+    fn pixel_ptr(&self, x: usize, y: usize) -> *mut u8 {
+        unsafe {
+            self.pixels_base
+                .add(x * self.layout.bytes_per_pixel + y * self.pitch())
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.pixels_width
+    }
+
+    pub fn height(&self) -> usize {
+        self.pixels_height
+    }
+
+    pub fn layout(&self) -> PixelLayout {
+        self.layout
+    }
+
+    unsafe fn set_pixel_raw(&self, x: usize, y: usize, color: FramebufferColor) {
+        let pixel = self.pixel_ptr(x, y);
+        if self.layout.bytes_per_pixel == 4 {
+            unsafe { pixel.cast::<FramebufferColor>().write(color) };
+        } else {
+            let bytes = color.0.to_le_bytes();
+            unsafe {
+                core::ptr::copy_nonoverlapping(bytes.as_ptr(), pixel, self.layout.bytes_per_pixel)
+            };
+        }
+    }
+
+    unsafe fn get_pixel_raw(&self, x: usize, y: usize) -> FramebufferColor {
+        let pixel = self.pixel_ptr(x, y);
+        if self.layout.bytes_per_pixel == 4 {
+            unsafe { pixel.cast::<FramebufferColor>().read() }
+        } else {
+            let mut bytes = [0; 4];
+            unsafe {
+                core::ptr::copy_nonoverlapping(
+                    pixel,
+                    bytes.as_mut_ptr(),
+                    self.layout.bytes_per_pixel,
+                )
+            };
+            FramebufferColor(u32::from_le_bytes(bytes))
+        }
+    }
+
+    // 32 bit pixels go through the simd row paths, smaller ones are written a pixel at a time
+    unsafe fn fill_row_raw(&self, x: usize, y: usize, length: usize, color: FramebufferColor) {
+        if self.layout.bytes_per_pixel == 4 {
+            unsafe { fill_row(self.pixel_ptr(x, y).cast(), length, color) };
+        } else {
+            for x in x..x + length {
+                unsafe { self.set_pixel_raw(x, y, color) };
+            }
+        }
+    }
+
+    unsafe fn copy_row_raw(
+        &self,
+        x: usize,
+        y: usize,
+        source: *const FramebufferColor,
+        length: usize,
+    ) {
+        if self.layout.bytes_per_pixel == 4 {
+            unsafe { copy_row(self.pixel_ptr(x, y).cast(), source, length) };
+        } else {
+            for index in 0..length {
+                unsafe { self.set_pixel_raw(x + index, y, source.add(index).read()) };
+            }
+        }
+    }
+
+    pub fn set_pixel(&self, x: usize, y: usize, color: FramebufferColor) {
+        if x < self.pixels_width && y < self.pixels_height {
+            unsafe { self.set_pixel_raw(x, y, color) };
+            unsafe { asm!("/* {0} */", in(reg) self.pixels_base, options(nostack)) };
+        }
+    }
+
+    pub fn fill(
+        &self,
+        left: usize,
+        top: usize,
+        width: usize,
+        height: usize,
+        color: FramebufferColor,
+    ) {
+        let top = top.min(self.pixels_height);
+        let bottom = top.saturating_add(height).min(self.pixels_height);
+        let left = left.min(self.pixels_width);
+        let right = left.saturating_add(width).min(self.pixels_width);
+        for y in top..bottom {
+            unsafe { self.fill_row_raw(left, y, right - left, color) };
+        }
+        unsafe { asm!("/* {0} */", in(reg) self.pixels_base, options(nostack)) };
+    }
+
+    pub fn copy_fullscreen(&self, screen: &FramebufferColorPixels) {
+        assert_eq!(screen.width(), self.width());
+        assert_eq!(screen.height(), self.height());
+        assert_eq!(screen.layout(), self.layout);
+
+        for y in 0..self.pixels_height {
+            unsafe {
+                self.copy_row_raw(
+                    0,
+                    y,
+                    screen.pixels().add(y * self.pixels_width),
+                    self.pixels_width,
+                );
+            }
+        }
+
+        unsafe { asm!("/* {0} */", in(reg) self.pixels_base, options(nostack)) };
+    }
+
+    // copies only the parts of `screen` that changed since the last present
+    pub fn present(&self, screen: &mut FramebufferColorPixels) {
+        assert_eq!(screen.width(), self.width());
+        assert_eq!(screen.height(), self.height());
+        assert_eq!(screen.layout(), self.layout);
+
+        for rect in screen.damage() {
+            for y in rect.top..rect.bottom() {
+                unsafe {
+                    self.copy_row_raw(
+                        rect.left,
+                        y,
+                        screen.pixels().add(rect.left + y * self.pixels_width),
+                        rect.width,
+                    );
+                }
+            }
+        }
+        screen.clear_damage();
+
+        unsafe { asm!("/* {0} */", in(reg) self.pixels_base, options(nostack)) };
+    }
+}
+
+impl Screen for &Framebuffer {
+    fn width(&self) -> usize {
+        self.pixels_width
+    }
+
+    fn height(&self) -> usize {
+        self.pixels_height
+    }
+
+    unsafe fn set_pixel_unchecked(&mut self, x: usize, y: usize, color: Color) {
+        unsafe { self.set_pixel_raw(x, y, self.layout.encode(color)) };
+        unsafe { asm!("/* {0} */", in(reg) self.pixels_base, options(nostack)) };
+    }
+
+    unsafe fn get_pixel_unchecked(&self, x: usize, y: usize) -> Color {
+        unsafe { asm!("/* {0} */", in(reg) self.pixels_base, options(nostack)) };
+        unsafe { self.layout.decode(self.get_pixel_raw(x, y)) }
+    }
+
+    fn set_pixel(&mut self, x: usize, y: usize, color: Color) {
+        (*self).set_pixel(x, y, self.layout.encode(color))
+    }
+
+    fn get_pixel(&self, x: usize, y: usize) -> Option<Color> {
+        if x < self.width() && y < self.height() {
+            Some(unsafe { self.get_pixel_unchecked(x, y) })
+        } else {
+            None
+        }
+    }
+
+    fn fill(&mut self, left: usize, top: usize, width: usize, height: usize, color: Color) {
+        (*self).fill(left, top, width, height, self.layout.encode(color))
+    }
+
+    fn copy(&mut self, screen: &dyn Screen, left: usize, top: usize) {
+        let width = screen.width();
+        let height = screen.height();
+
+        let top = top.min(self.pixels_height);
+        let bottom = top.saturating_add(height).min(self.pixels_height);
+        let left = left.min(self.pixels_width);
+        let right = left.saturating_add(width).min(self.pixels_width);
+
+        for y in top..bottom {
+            for x in left..right {
+                unsafe {
+                    self.set_pixel_raw(
+                        x,
+                        y,
+                        self.layout
+                            .encode(screen.get_pixel_unchecked(x - left, y - top)),
+                    );
+                }
+            }
+        }
+
+        unsafe { asm!("/* {0} */", in(reg) self.pixels_base, options(nostack)) };
+    }
+}
+
+// a pixel in whatever `PixelLayout` the buffer it came from uses
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(transparent)]
+pub struct FramebufferColor(pub u32);
+
+static FRAMEBUFFER: SyncUnsafeCell<Framebuffer> = SyncUnsafeCell::new(Framebuffer {
+    layout: PixelLayout::new(PixelFormat::Rgb),
+    pixels_base: core::ptr::null_mut(),
+    pixels_width: 0,
+    pixels_height: 0,
+    pixels_per_scanline: 0,
+});
+
+pub fn framebuffer() -> &'static Framebuffer {
+    unsafe { &*FRAMEBUFFER.get() }
+}
+
+pub unsafe fn init_framebuffer(info: &FramebufferInfo) {
+    unsafe {
+        *FRAMEBUFFER.get() = Framebuffer {
+            layout: PixelLayout::new(info.format),
+            pixels_base: core::ptr::with_exposed_provenance_mut(info.base),
+            pixels_width: info.width,
+            pixels_height: info.height,
+            pixels_per_scanline: info.pixels_per_scanline,
+        };
+    }
+}
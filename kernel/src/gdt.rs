@@ -73,7 +73,7 @@ unsafe fn reload_kernel_segments() {
     unsafe {
         asm!(
             "push {kernel_code}",
-            "lea rax, [2f]",
+            "lea rax, [rip + 2f]",
             "push rax",
             "retfq",
             "2:",
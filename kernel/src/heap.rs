@@ -0,0 +1,142 @@
+use crate::{interrupt_safe_mutex::InterruptSafeMutex, page_allocator::PAGE_ALLOCATOR};
+use core::{alloc::Layout, ptr::NonNull};
+
+const PAGE_SIZE: usize = 4096;
+const MIN_BLOCK_SIZE: usize = 16;
+const SIZE_CLASS_COUNT: usize = 8;
+
+// anything bigger than the largest size class goes straight to the page allocator
+const MAX_BLOCK_SIZE: usize = MIN_BLOCK_SIZE << (SIZE_CLASS_COUNT - 1);
+
+const _: () = assert!(MAX_BLOCK_SIZE <= PAGE_SIZE);
+
+struct FreeBlock {
+    next: Option<NonNull<FreeBlock>>,
+}
+
+pub struct Heap {
+    free_lists: [Option<NonNull<FreeBlock>>; SIZE_CLASS_COUNT],
+    pages: usize,
+}
+
+unsafe impl Send for Heap {}
+
+fn size_class(layout: Layout) -> Option<usize> {
+    let size = layout
+        .size()
+        .max(layout.align())
+        .max(MIN_BLOCK_SIZE)
+        .next_power_of_two();
+    if size <= MAX_BLOCK_SIZE {
+        Some((size / MIN_BLOCK_SIZE).trailing_zeros() as usize)
+    } else {
+        None
+    }
+}
+
+const fn block_size(class: usize) -> usize {
+    MIN_BLOCK_SIZE << class
+}
+
+impl Heap {
+    pub const fn new() -> Self {
+        Self {
+            free_lists: [None; _],
+            pages: 0,
+        }
+    }
+
+    // the number of pages that have been split up into small blocks
+    pub fn pages(&self) -> usize {
+        self.pages
+    }
+
+    fn refill(&mut self, class: usize) -> Option<()> {
+        let page = PAGE_ALLOCATOR
+            .with(|alloc| alloc.allocate(Layout::from_size_align(PAGE_SIZE, PAGE_SIZE).unwrap()))?;
+        self.pages += 1;
+
+        let block_size = block_size(class);
+        for offset in (0..PAGE_SIZE).step_by(block_size).rev() {
+            let block = core::ptr::with_exposed_provenance_mut::<FreeBlock>(page + offset);
+            unsafe {
+                block.write(FreeBlock {
+                    next: self.free_lists[class],
+                });
+                self.free_lists[class] = Some(NonNull::new_unchecked(block));
+            }
+        }
+        Some(())
+    }
+
+    pub fn allocate(&mut self, layout: Layout) -> Option<NonNull<u8>> {
+        let Some(class) = size_class(layout) else {
+            let address = PAGE_ALLOCATOR.with(|alloc| alloc.allocate(layout))?;
+            return NonNull::new(core::ptr::with_exposed_provenance_mut(address));
+        };
+
+        if self.free_lists[class].is_none() {
+            self.refill(class)?;
+        }
+
+        let block = self.free_lists[class]?;
+        self.free_lists[class] = unsafe { block.as_ref().next };
+        Some(block.cast())
+    }
+
+    pub unsafe fn free(&mut self, ptr: NonNull<u8>, layout: Layout) {
+        let Some(class) = size_class(layout) else {
+            PAGE_ALLOCATOR.with(|alloc| unsafe { alloc.free(ptr.as_ptr().addr(), layout) });
+            return;
+        };
+
+        let block = ptr.cast::<FreeBlock>();
+        unsafe {
+            block.write(FreeBlock {
+                next: self.free_lists[class],
+            });
+        }
+        self.free_lists[class] = Some(block);
+    }
+
+    pub unsafe fn reallocate(
+        &mut self,
+        ptr: NonNull<u8>,
+        layout: Layout,
+        new_size: usize,
+    ) -> Option<NonNull<u8>> {
+        let new_layout = Layout::from_size_align(new_size, layout.align()).ok()?;
+
+        match (size_class(layout), size_class(new_layout)) {
+            (Some(class), Some(new_class)) if class == new_class => return Some(ptr),
+            (None, None) => {
+                let resized = PAGE_ALLOCATOR.with(|alloc| unsafe {
+                    alloc.resize_in_place(ptr.as_ptr().addr(), layout, new_size)
+                });
+                if resized {
+                    return Some(ptr);
+                }
+            }
+            _ => {}
+        }
+
+        let new_ptr = self.allocate(new_layout)?;
+        unsafe {
+            core::ptr::copy_nonoverlapping(
+                ptr.as_ptr(),
+                new_ptr.as_ptr(),
+                layout.size().min(new_size),
+            );
+            self.free(ptr, layout);
+        }
+        Some(new_ptr)
+    }
+}
+
+impl Default for Heap {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub static HEAP: InterruptSafeMutex<Heap> = InterruptSafeMutex::new(Heap::new());
@@ -0,0 +1,149 @@
+use crate::{
+    framebuffer::Color,
+    image::inflate::InflateError,
+    screen::{Pixels, RgbaPixels},
+};
+
+pub mod bmp;
+pub mod deflate;
+pub mod inflate;
+pub mod png;
+pub mod qoi;
+pub mod tga;
+
+// anything bigger is much more likely to be a corrupt header than a real image
+pub const MAX_PIXELS: usize = 1 << 26;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageError {
+    UnknownFormat,
+    Truncated,
+    InvalidHeader,
+    // zero, or more than `MAX_PIXELS` altogether
+    InvalidSize { width: usize, height: usize },
+    UnsupportedImageType(u8),
+    UnsupportedBitsPerPixel(u16),
+    UnsupportedCompression(u32),
+    UnsupportedColorType { color_type: u8, bit_depth: u8 },
+    UnsupportedChunk([u8; 4]),
+    MissingPalette,
+    ColorIndexOutOfRange(usize),
+    InvalidFilter(u8),
+    // the compressed pixel data doesn't fit the size in the header
+    CorruptData,
+    ChecksumMismatch,
+    Inflate(InflateError),
+}
+
+impl From<InflateError> for ImageError {
+    fn from(error: InflateError) -> Self {
+        Self::Inflate(error)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageFormat {
+    Bmp,
+    Tga,
+    Qoi,
+    Png,
+}
+
+impl ImageFormat {
+    // tga has no magic number, so anything else is assumed to be one
+    pub fn detect(bytes: &[u8]) -> Self {
+        if bytes.starts_with(&png::SIGNATURE) {
+            Self::Png
+        } else if bytes.starts_with(qoi::MAGIC) {
+            Self::Qoi
+        } else if bytes.starts_with(bmp::MAGIC) {
+            Self::Bmp
+        } else {
+            Self::Tga
+        }
+    }
+}
+
+pub fn decode(bytes: &[u8]) -> Result<RgbaPixels, ImageError> {
+    match ImageFormat::detect(bytes) {
+        ImageFormat::Bmp => bmp::decode(bytes),
+        ImageFormat::Tga => tga::decode(bytes),
+        ImageFormat::Qoi => qoi::decode(bytes),
+        ImageFormat::Png => png::decode(bytes),
+    }
+}
+
+// for images that only ever get copied to the screen, with any transparency put over `background`
+pub fn decode_opaque(bytes: &[u8], background: Color) -> Result<Pixels, ImageError> {
+    Ok(decode(bytes)?.flatten(background))
+}
+
+fn check_size(width: usize, height: usize) -> Result<(), ImageError> {
+    if width == 0
+        || height == 0
+        || width
+            .checked_mul(height)
+            .is_none_or(|pixels| pixels > MAX_PIXELS)
+    {
+        return Err(ImageError::InvalidSize { width, height });
+    }
+    Ok(())
+}
+
+// reads the header fields one after another, failing with `Truncated` past the end
+struct Reader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes, position: 0 }
+    }
+
+    fn seek(&mut self, position: usize) -> Result<(), ImageError> {
+        if position > self.bytes.len() {
+            return Err(ImageError::Truncated);
+        }
+        self.position = position;
+        Ok(())
+    }
+
+    fn skip(&mut self, length: usize) -> Result<(), ImageError> {
+        self.bytes(length).map(|_| ())
+    }
+
+    fn bytes(&mut self, length: usize) -> Result<&'a [u8], ImageError> {
+        let bytes = self
+            .bytes
+            .get(self.position..)
+            .and_then(|bytes| bytes.get(..length))
+            .ok_or(ImageError::Truncated)?;
+        self.position += length;
+        Ok(bytes)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], ImageError> {
+        Ok(self.bytes(N)?.try_into().unwrap())
+    }
+
+    fn u8(&mut self) -> Result<u8, ImageError> {
+        Ok(self.array::<1>()?[0])
+    }
+
+    fn u16_le(&mut self) -> Result<u16, ImageError> {
+        Ok(u16::from_le_bytes(self.array()?))
+    }
+
+    fn u32_le(&mut self) -> Result<u32, ImageError> {
+        Ok(u32::from_le_bytes(self.array()?))
+    }
+
+    fn i32_le(&mut self) -> Result<i32, ImageError> {
+        Ok(i32::from_le_bytes(self.array()?))
+    }
+
+    fn u32_be(&mut self) -> Result<u32, ImageError> {
+        Ok(u32::from_be_bytes(self.array()?))
+    }
+}
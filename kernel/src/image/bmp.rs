@@ -0,0 +1,335 @@
+use crate::{
+    framebuffer::{Color, Rgba},
+    image::{ImageError, Reader, check_size},
+    screen::{RgbaPixels, Screen},
+};
+use alloc::{vec, vec::Vec};
+
+pub const MAGIC: &[u8] = b"BM";
+
+const FILE_HEADER_SIZE: usize = 14;
+// os/2 style, with 16 bit sizes and 3 byte palette entries
+const CORE_HEADER_SIZE: u32 = 12;
+const INFO_HEADER_SIZE: u32 = 40;
+
+const COMPRESSION_RGB: u32 = 0;
+const COMPRESSION_RLE8: u32 = 1;
+const COMPRESSION_RLE4: u32 = 2;
+const COMPRESSION_BITFIELDS: u32 = 3;
+const COMPRESSION_ALPHA_BITFIELDS: u32 = 6;
+
+// a channel of a 16 or 32 bit pixel, scaled up or down to 8 bits
+#[derive(Clone, Copy)]
+struct Mask {
+    mask: u32,
+    shift: u32,
+}
+
+impl Mask {
+    fn new(mask: u32) -> Self {
+        Self {
+            mask,
+            shift: mask.trailing_zeros() % 32,
+        }
+    }
+
+    fn get(self, pixel: u32) -> u8 {
+        let max = (self.mask >> self.shift) as u64;
+        (((pixel & self.mask) >> self.shift) as u64 * 255)
+            .checked_div(max)
+            .unwrap_or(0) as u8
+    }
+}
+
+struct Masks {
+    r: Mask,
+    g: Mask,
+    b: Mask,
+    // no alpha mask means every pixel is opaque
+    a: Option<Mask>,
+}
+
+impl Masks {
+    fn get(&self, pixel: u32) -> Rgba {
+        let color = Color {
+            r: self.r.get(pixel),
+            g: self.g.get(pixel),
+            b: self.b.get(pixel),
+        };
+        Rgba::new(color, self.a.map_or(255, |a| a.get(pixel)))
+    }
+}
+
+// the palette indices of a run length encoded image, anything it skips over is left as 0
+fn decode_rle(
+    reader: &mut Reader<'_>,
+    width: usize,
+    height: usize,
+    nibbles: bool,
+) -> Result<Vec<u8>, ImageError> {
+    let mut indices = vec![0u8; width * height];
+    let (mut x, mut y) = (0, 0);
+    let mut put = |x: &mut usize, y: usize, index: u8| {
+        if *x >= width || y >= height {
+            return Err(ImageError::CorruptData);
+        }
+        indices[*x + y * width] = index;
+        *x += 1;
+        Ok(())
+    };
+
+    loop {
+        let [count, value] = reader.array()?;
+        if count != 0 {
+            for pixel in 0..count {
+                let index = match nibbles {
+                    true if pixel % 2 == 0 => value >> 4,
+                    true => value & 0x0f,
+                    false => value,
+                };
+                put(&mut x, y, index)?;
+            }
+            continue;
+        }
+        match value {
+            0 => {
+                x = 0;
+                y += 1;
+            }
+            1 => break,
+            2 => {
+                let [right, down] = reader.array()?;
+                x += right as usize;
+                y += down as usize;
+            }
+            // that many indices as they are, padded to a multiple of 2 bytes
+            count => {
+                let length = if nibbles {
+                    (count as usize).div_ceil(2)
+                } else {
+                    count as usize
+                };
+                let bytes = reader.bytes(length)?;
+                for pixel in 0..count as usize {
+                    let index = if nibbles {
+                        (bytes[pixel / 2] >> (4 - pixel % 2 * 4)) & 0x0f
+                    } else {
+                        bytes[pixel]
+                    };
+                    put(&mut x, y, index)?;
+                }
+                reader.skip(length % 2)?;
+            }
+        }
+    }
+    Ok(indices)
+}
+
+pub fn decode(bytes: &[u8]) -> Result<RgbaPixels, ImageError> {
+    let mut reader = Reader::new(bytes);
+    if reader.bytes(MAGIC.len())? != MAGIC {
+        return Err(ImageError::UnknownFormat);
+    }
+    // the file size and two reserved fields
+    reader.skip(8)?;
+    let pixel_offset = reader.u32_le()? as usize;
+    let header_size = reader.u32_le()?;
+
+    let (width, height, bits_per_pixel, compression, colors_used) =
+        if header_size == CORE_HEADER_SIZE {
+            let width = reader.u16_le()? as i32;
+            let height = reader.u16_le()? as i32;
+            let _planes = reader.u16_le()?;
+            let bits_per_pixel = reader.u16_le()?;
+            (width, height, bits_per_pixel, COMPRESSION_RGB, 0)
+        } else if header_size >= INFO_HEADER_SIZE {
+            let width = reader.i32_le()?;
+            let height = reader.i32_le()?;
+            let _planes = reader.u16_le()?;
+            let bits_per_pixel = reader.u16_le()?;
+            let compression = reader.u32_le()?;
+            // the image size and resolution
+            reader.skip(12)?;
+            let colors_used = reader.u32_le()?;
+            (width, height, bits_per_pixel, compression, colors_used)
+        } else {
+            return Err(ImageError::InvalidHeader);
+        };
+
+    // a negative height means the rows are stored top to bottom instead of bottom to top
+    let top_down = height < 0;
+    let width = usize::try_from(width).map_err(|_| ImageError::InvalidHeader)?;
+    let height = height.unsigned_abs() as usize;
+    check_size(width, height)?;
+
+    let valid = match compression {
+        COMPRESSION_RGB => matches!(bits_per_pixel, 1 | 2 | 4 | 8 | 16 | 24 | 32),
+        COMPRESSION_RLE8 => bits_per_pixel == 8,
+        COMPRESSION_RLE4 => bits_per_pixel == 4,
+        COMPRESSION_BITFIELDS | COMPRESSION_ALPHA_BITFIELDS => matches!(bits_per_pixel, 16 | 32),
+        _ => return Err(ImageError::UnsupportedCompression(compression)),
+    };
+    if !valid {
+        return Err(ImageError::UnsupportedBitsPerPixel(bits_per_pixel));
+    }
+    if (compression == COMPRESSION_RLE8 || compression == COMPRESSION_RLE4) && top_down {
+        return Err(ImageError::InvalidHeader);
+    }
+
+    let masks = match (compression, bits_per_pixel) {
+        // straight after the info header, newer headers just grew to include them
+        (COMPRESSION_BITFIELDS | COMPRESSION_ALPHA_BITFIELDS, _) => {
+            reader.seek(FILE_HEADER_SIZE + INFO_HEADER_SIZE as usize)?;
+            let r = Mask::new(reader.u32_le()?);
+            let g = Mask::new(reader.u32_le()?);
+            let b = Mask::new(reader.u32_le()?);
+            let a = if compression == COMPRESSION_ALPHA_BITFIELDS || header_size >= 56 {
+                Some(reader.u32_le()?)
+                    .filter(|&mask| mask != 0)
+                    .map(Mask::new)
+            } else {
+                None
+            };
+            Masks { r, g, b, a }
+        }
+        (_, 16) => Masks {
+            r: Mask::new(0x7c00),
+            g: Mask::new(0x03e0),
+            b: Mask::new(0x001f),
+            a: None,
+        },
+        _ => Masks {
+            r: Mask::new(0x00ff0000),
+            g: Mask::new(0x0000ff00),
+            b: Mask::new(0x000000ff),
+            a: None,
+        },
+    };
+
+    // the palette follows the header, and the masks too for a 40 byte header
+    let mut palette = Vec::new();
+    if bits_per_pixel <= 8 {
+        let mut palette_offset = FILE_HEADER_SIZE + header_size as usize;
+        if header_size == INFO_HEADER_SIZE {
+            palette_offset += match compression {
+                COMPRESSION_BITFIELDS => 12,
+                COMPRESSION_ALPHA_BITFIELDS => 16,
+                _ => 0,
+            };
+        }
+        reader.seek(palette_offset)?;
+        let entry_size = if header_size == CORE_HEADER_SIZE {
+            3
+        } else {
+            4
+        };
+        let count = match colors_used as usize {
+            0 => 1 << bits_per_pixel,
+            count => count.min(1 << bits_per_pixel),
+        };
+        for _ in 0..count {
+            let entry = reader.bytes(entry_size)?;
+            palette.push(Rgba::opaque(Color {
+                r: entry[2],
+                g: entry[1],
+                b: entry[0],
+            }));
+        }
+    }
+    let palette_color = |index: u8| {
+        palette
+            .get(index as usize)
+            .copied()
+            .ok_or(ImageError::ColorIndexOutOfRange(index as usize))
+    };
+
+    reader.seek(pixel_offset)?;
+    let mut pixels = Vec::with_capacity(width * height);
+    if compression == COMPRESSION_RLE8 || compression == COMPRESSION_RLE4 {
+        let indices = decode_rle(&mut reader, width, height, compression == COMPRESSION_RLE4)?;
+        for row in indices.chunks_exact(width).rev() {
+            for &index in row {
+                pixels.push(palette_color(index)?);
+            }
+        }
+    } else {
+        // rows are padded to a multiple of 4 bytes
+        let stride = (width * bits_per_pixel as usize).div_ceil(32) * 4;
+        let data = reader.bytes(stride * height)?;
+        for y in 0..height {
+            let row = if top_down { y } else { height - 1 - y };
+            let row = &data[row * stride..(row + 1) * stride];
+            for x in 0..width {
+                pixels.push(match bits_per_pixel {
+                    32 => masks.get(u32::from_le_bytes(
+                        row[x * 4..x * 4 + 4].try_into().unwrap(),
+                    )),
+                    24 => Rgba::opaque(Color {
+                        r: row[x * 3 + 2],
+                        g: row[x * 3 + 1],
+                        b: row[x * 3],
+                    }),
+                    16 => masks.get(u16::from_le_bytes([row[x * 2], row[x * 2 + 1]]) as u32),
+                    8 => palette_color(row[x])?,
+                    // packed most significant first
+                    _ => {
+                        let bits = bits_per_pixel as usize;
+                        let shift = 8 - bits - x * bits % 8;
+                        palette_color((row[x * bits / 8] >> shift) & ((1 << bits) - 1))?
+                    }
+                });
+            }
+        }
+    }
+
+    Ok(RgbaPixels::from_vec(pixels, width, height))
+}
+
+// 24 bits per pixel and stored top to bottom, so each row can be written out as soon as it's read
+// doesn't allocate, so it still works from the panic handler when the heap is locked or broken
+pub fn encode<S: Screen + ?Sized>(screen: &S, write: &mut impl FnMut(&[u8])) {
+    const PIXEL_OFFSET: usize = FILE_HEADER_SIZE + INFO_HEADER_SIZE as usize;
+    // pixels are written this many at a time
+    const CHUNK_PIXELS: usize = 256;
+
+    let width = screen.width();
+    let height = screen.height();
+    let stride = (width * 3).next_multiple_of(4);
+
+    let mut header = [0; PIXEL_OFFSET];
+    let mut length = 0;
+    for part in [
+        MAGIC,
+        &((PIXEL_OFFSET + stride * height) as u32).to_le_bytes(),
+        &[0; 4],
+        &(PIXEL_OFFSET as u32).to_le_bytes(),
+        &INFO_HEADER_SIZE.to_le_bytes(),
+        &(width as i32).to_le_bytes(),
+        &(-(height as i32)).to_le_bytes(),
+        // 1 plane, then the bits per pixel
+        &1u16.to_le_bytes(),
+        &24u16.to_le_bytes(),
+        &COMPRESSION_RGB.to_le_bytes(),
+        &((stride * height) as u32).to_le_bytes(),
+        // the resolution and palette size, which don't matter here
+        &[0; 16],
+    ] {
+        header[length..length + part.len()].copy_from_slice(part);
+        length += part.len();
+    }
+    write(&header);
+
+    let mut chunk = [0u8; CHUNK_PIXELS * 3];
+    for y in 0..height {
+        for left in (0..width).step_by(CHUNK_PIXELS) {
+            let right = (left + CHUNK_PIXELS).min(width);
+            for x in left..right {
+                let color = unsafe { screen.get_pixel_unchecked(x, y) };
+                let index = (x - left) * 3;
+                chunk[index..index + 3].copy_from_slice(&[color.b, color.g, color.r]);
+            }
+            write(&chunk[..(right - left) * 3]);
+        }
+        write(&[0; 3][..stride - width * 3]);
+    }
+}
@@ -0,0 +1,117 @@
+use crate::image::inflate::{END_OF_BLOCK, LENGTH_BASE, LENGTH_EXTRA_BITS, update_adler32};
+use alloc::vec::Vec;
+
+const MIN_MATCH: usize = 3;
+const MAX_MATCH: usize = 258;
+
+// the fixed huffman code for a literal or length symbol, as (code, length in bits)
+fn fixed_code(symbol: u16) -> (u16, u32) {
+    match symbol {
+        0..=143 => (0x30 + symbol, 8),
+        144..=255 => (0x190 + symbol - 144, 9),
+        256..=279 => (symbol - 256, 7),
+        _ => (0xc0 + symbol - 280, 8),
+    }
+}
+
+// a zlib stream made of a single fixed huffman block that only matches runs of the same byte,
+// which is cheap enough to do while streaming and still shrinks screenshots a lot after png filtering
+pub struct ZlibEncoder {
+    output: Vec<u8>,
+    bits: u64,
+    count: u32,
+    previous: Option<u8>,
+    adler: u32,
+}
+
+impl Default for ZlibEncoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ZlibEncoder {
+    pub fn new() -> Self {
+        let mut encoder = Self {
+            // deflate with a 32 KiB window and the fastest compression level, which is a multiple of 31 as it should be
+            output: Vec::from([0x78, 0x01]),
+            bits: 0,
+            count: 0,
+            previous: None,
+            adler: 1,
+        };
+        // the last block, with fixed codes
+        encoder.write_bits(0b011, 3);
+        encoder
+    }
+
+    // deflate packs everything least significant bit first
+    fn write_bits(&mut self, bits: u32, count: u32) {
+        self.bits |= (bits as u64) << self.count;
+        self.count += count;
+        while self.count >= 8 {
+            self.output.push(self.bits as u8);
+            self.bits >>= 8;
+            self.count -= 8;
+        }
+    }
+
+    // except the huffman codes, which go most significant bit first
+    fn write_code(&mut self, symbol: u16) {
+        let (code, length) = fixed_code(symbol);
+        self.write_bits(code.reverse_bits() as u32 >> (16 - length), length);
+    }
+
+    fn write_run(&mut self, length: usize) {
+        let index = LENGTH_BASE
+            .iter()
+            .rposition(|&base| base as usize <= length)
+            .unwrap();
+        self.write_code(257 + index as u16);
+        self.write_bits(
+            (length - LENGTH_BASE[index] as usize) as u32,
+            LENGTH_EXTRA_BITS[index] as u32,
+        );
+        // a distance of 1, which is code 0 with no extra bits
+        self.write_bits(0, 5);
+    }
+
+    pub fn write(&mut self, bytes: &[u8]) {
+        self.adler = update_adler32(self.adler, bytes);
+
+        let mut index = 0;
+        while index < bytes.len() {
+            let byte = bytes[index];
+            if self.previous == Some(byte) {
+                let run = bytes[index..]
+                    .iter()
+                    .take(MAX_MATCH)
+                    .take_while(|&&other| other == byte)
+                    .count();
+                if run >= MIN_MATCH {
+                    self.write_run(run);
+                    index += run;
+                    continue;
+                }
+            }
+            self.write_code(byte as u16);
+            self.previous = Some(byte);
+            index += 1;
+        }
+    }
+
+    // what has been compressed so far, for streaming it out before the end
+    pub fn output(&mut self) -> &mut Vec<u8> {
+        &mut self.output
+    }
+
+    pub fn finish(mut self) -> Vec<u8> {
+        self.write_code(END_OF_BLOCK);
+        if self.count > 0 {
+            self.write_bits(0, 8 - self.count);
+        }
+        let adler = self.adler;
+        self.output.extend_from_slice(&adler.to_be_bytes());
+        self.output
+    }
+}
@@ -0,0 +1,348 @@
+use alloc::vec::Vec;
+
+// deflate (rfc 1951) and the zlib wrapper around it (rfc 1950), as used by png
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InflateError {
+    Truncated,
+    InvalidZlibHeader,
+    // a preset dictionary, which png never uses
+    UnsupportedDictionary,
+    InvalidBlockType,
+    StoredLengthMismatch,
+    InvalidCodeLengths,
+    InvalidSymbol(u16),
+    DistanceTooFar(usize),
+    // more output than the caller said to expect
+    OutputTooLarge,
+    ChecksumMismatch,
+}
+
+const MAX_BITS: usize = 15;
+const LITERAL_LENGTH_CODES: usize = 288;
+const DISTANCE_CODES: usize = 30;
+pub const END_OF_BLOCK: u16 = 256;
+
+pub const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+pub const LENGTH_EXTRA_BITS: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+const DISTANCE_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DISTANCE_EXTRA_BITS: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+// the order the code length code lengths are stored in, most likely to be used first
+const CODE_LENGTH_ORDER: [usize; 19] = [
+    16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15,
+];
+
+// least significant bit first, as deflate packs everything except the huffman codes
+struct Bits<'a> {
+    bytes: &'a [u8],
+    position: usize,
+    buffer: u32,
+    count: u32,
+}
+
+impl<'a> Bits<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        Self {
+            bytes,
+            position: 0,
+            buffer: 0,
+            count: 0,
+        }
+    }
+
+    fn bits(&mut self, count: u32) -> Result<u32, InflateError> {
+        while self.count < count {
+            let byte = *self
+                .bytes
+                .get(self.position)
+                .ok_or(InflateError::Truncated)?;
+            self.position += 1;
+            self.buffer |= (byte as u32) << self.count;
+            self.count += 8;
+        }
+        let value = self.buffer & ((1 << count) - 1);
+        self.buffer >>= count;
+        self.count -= count;
+        Ok(value)
+    }
+
+    // stored blocks start on a byte boundary
+    fn align_to_byte(&mut self) {
+        self.buffer = 0;
+        self.count = 0;
+    }
+
+    fn bytes(&mut self, length: usize) -> Result<&'a [u8], InflateError> {
+        let bytes = self
+            .bytes
+            .get(self.position..)
+            .and_then(|bytes| bytes.get(..length))
+            .ok_or(InflateError::Truncated)?;
+        self.position += length;
+        Ok(bytes)
+    }
+}
+
+// canonical huffman codes only need the number of codes of each length and the symbols in code order
+struct Huffman {
+    counts: [u16; MAX_BITS + 1],
+    symbols: [u16; LITERAL_LENGTH_CODES],
+}
+
+impl Huffman {
+    fn new(lengths: &[u8]) -> Result<Self, InflateError> {
+        let mut counts = [0u16; MAX_BITS + 1];
+        for &length in lengths {
+            counts[length as usize] += 1;
+        }
+        counts[0] = 0;
+
+        // more codes of a length than there's room for can't be decoded, fewer is allowed
+        let mut left = 1i32;
+        for &count in &counts[1..] {
+            left = (left << 1) - count as i32;
+            if left < 0 {
+                return Err(InflateError::InvalidCodeLengths);
+            }
+        }
+
+        let mut offsets = [0u16; MAX_BITS + 1];
+        for length in 1..MAX_BITS {
+            offsets[length + 1] = offsets[length] + counts[length];
+        }
+        let mut symbols = [0u16; LITERAL_LENGTH_CODES];
+        for (symbol, &length) in lengths.iter().enumerate() {
+            if length != 0 {
+                symbols[offsets[length as usize] as usize] = symbol as u16;
+                offsets[length as usize] += 1;
+            }
+        }
+        Ok(Self { counts, symbols })
+    }
+
+    // the codes are stored most significant bit first, so they have to be read a bit at a time
+    fn decode(&self, bits: &mut Bits<'_>) -> Result<u16, InflateError> {
+        let mut code = 0i32;
+        let mut first = 0i32;
+        let mut index = 0i32;
+        for &count in &self.counts[1..] {
+            code |= bits.bits(1)? as i32;
+            let count = count as i32;
+            if code - first < count {
+                return Ok(self.symbols[(index + code - first) as usize]);
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+        Err(InflateError::InvalidSymbol(code as u16))
+    }
+}
+
+fn fixed_codes() -> (Huffman, Huffman) {
+    let mut lengths = [0u8; LITERAL_LENGTH_CODES];
+    lengths[..144].fill(8);
+    lengths[144..256].fill(9);
+    lengths[256..280].fill(7);
+    lengths[280..].fill(8);
+    let literal_length = Huffman::new(&lengths).unwrap();
+    let distance = Huffman::new(&[5; DISTANCE_CODES]).unwrap();
+    (literal_length, distance)
+}
+
+fn dynamic_codes(bits: &mut Bits<'_>) -> Result<(Huffman, Huffman), InflateError> {
+    let literal_length_count = bits.bits(5)? as usize + 257;
+    let distance_count = bits.bits(5)? as usize + 1;
+    let code_length_count = bits.bits(4)? as usize + 4;
+    if literal_length_count > 286 || distance_count > DISTANCE_CODES {
+        return Err(InflateError::InvalidCodeLengths);
+    }
+
+    let mut code_lengths = [0u8; 19];
+    for &index in &CODE_LENGTH_ORDER[..code_length_count] {
+        code_lengths[index] = bits.bits(3)? as u8;
+    }
+    let code_length_code = Huffman::new(&code_lengths)?;
+
+    // both sets of lengths are run length encoded together, so repeats can cross from one to the other
+    let mut lengths = [0u8; 286 + DISTANCE_CODES];
+    let total = literal_length_count + distance_count;
+    let mut index = 0;
+    while index < total {
+        let symbol = code_length_code.decode(bits)?;
+        let (length, repeat) = match symbol {
+            0..=15 => (symbol as u8, 1),
+            16 => {
+                let previous = *index
+                    .checked_sub(1)
+                    .and_then(|index| lengths.get(index))
+                    .ok_or(InflateError::InvalidCodeLengths)?;
+                (previous, 3 + bits.bits(2)? as usize)
+            }
+            17 => (0, 3 + bits.bits(3)? as usize),
+            18 => (0, 11 + bits.bits(7)? as usize),
+            _ => return Err(InflateError::InvalidSymbol(symbol)),
+        };
+        if index + repeat > total {
+            return Err(InflateError::InvalidCodeLengths);
+        }
+        lengths[index..index + repeat].fill(length);
+        index += repeat;
+    }
+
+    // without an end of block code there's no way for the block to finish
+    if lengths[END_OF_BLOCK as usize] == 0 {
+        return Err(InflateError::InvalidCodeLengths);
+    }
+    let literal_length = Huffman::new(&lengths[..literal_length_count])?;
+    let distance = Huffman::new(&lengths[literal_length_count..total])?;
+    Ok((literal_length, distance))
+}
+
+fn inflate_block(
+    bits: &mut Bits<'_>,
+    output: &mut Vec<u8>,
+    max_output: usize,
+    literal_length: &Huffman,
+    distance: &Huffman,
+) -> Result<(), InflateError> {
+    loop {
+        let symbol = literal_length.decode(bits)?;
+        if symbol < END_OF_BLOCK {
+            if output.len() >= max_output {
+                return Err(InflateError::OutputTooLarge);
+            }
+            output.push(symbol as u8);
+            continue;
+        }
+        if symbol == END_OF_BLOCK {
+            return Ok(());
+        }
+
+        let index = (symbol - 257) as usize;
+        if index >= LENGTH_BASE.len() {
+            return Err(InflateError::InvalidSymbol(symbol));
+        }
+        let length =
+            LENGTH_BASE[index] as usize + bits.bits(LENGTH_EXTRA_BITS[index] as u32)? as usize;
+
+        let symbol = distance.decode(bits)?;
+        let index = symbol as usize;
+        if index >= DISTANCE_BASE.len() {
+            return Err(InflateError::InvalidSymbol(symbol));
+        }
+        let distance =
+            DISTANCE_BASE[index] as usize + bits.bits(DISTANCE_EXTRA_BITS[index] as u32)? as usize;
+        if distance > output.len() {
+            return Err(InflateError::DistanceTooFar(distance));
+        }
+        if output.len() + length > max_output {
+            return Err(InflateError::OutputTooLarge);
+        }
+
+        // the copy can overlap what it's writing, which is how runs get repeated
+        let start = output.len() - distance;
+        for index in start..start + length {
+            output.push(output[index]);
+        }
+    }
+}
+
+// a raw deflate stream, returns how many bytes of `bytes` it took up
+pub fn inflate(
+    bytes: &[u8],
+    output: &mut Vec<u8>,
+    max_output: usize,
+) -> Result<usize, InflateError> {
+    let mut bits = Bits::new(bytes);
+    loop {
+        let last = bits.bits(1)? == 1;
+        match bits.bits(2)? {
+            0 => {
+                bits.align_to_byte();
+                let length = u16::from_le_bytes(bits.bytes(2)?.try_into().unwrap());
+                let complement = u16::from_le_bytes(bits.bytes(2)?.try_into().unwrap());
+                if length != !complement {
+                    return Err(InflateError::StoredLengthMismatch);
+                }
+                if output.len() + length as usize > max_output {
+                    return Err(InflateError::OutputTooLarge);
+                }
+                output.extend_from_slice(bits.bytes(length as usize)?);
+            }
+            1 => {
+                let (literal_length, distance) = fixed_codes();
+                inflate_block(&mut bits, output, max_output, &literal_length, &distance)?;
+            }
+            2 => {
+                let (literal_length, distance) = dynamic_codes(&mut bits)?;
+                inflate_block(&mut bits, output, max_output, &literal_length, &distance)?;
+            }
+            _ => return Err(InflateError::InvalidBlockType),
+        }
+        if last {
+            return Ok(bits.position);
+        }
+    }
+}
+
+pub fn adler32(bytes: &[u8]) -> u32 {
+    update_adler32(1, bytes)
+}
+
+// carries on from the checksum of everything before `bytes`
+pub fn update_adler32(adler: u32, bytes: &[u8]) -> u32 {
+    const MODULUS: u32 = 65521;
+    // the most bytes that can be summed before `b` could overflow
+    const CHUNK: usize = 5552;
+
+    let mut a = adler & 0xffff;
+    let mut b = adler >> 16;
+    for chunk in bytes.chunks(CHUNK) {
+        for &byte in chunk {
+            a += byte as u32;
+            b += a;
+        }
+        a %= MODULUS;
+        b %= MODULUS;
+    }
+    (b << 16) | a
+}
+
+// `max_output` is a limit, so a small corrupt or malicious stream can't fill up memory
+pub fn zlib_decompress(bytes: &[u8], max_output: usize) -> Result<Vec<u8>, InflateError> {
+    let [method, flags, ..] = *bytes else {
+        return Err(InflateError::Truncated);
+    };
+    if method & 0x0f != 8
+        || method >> 4 > 7
+        || !(method as u16 * 256 + flags as u16).is_multiple_of(31)
+    {
+        return Err(InflateError::InvalidZlibHeader);
+    }
+    if flags & 0x20 != 0 {
+        return Err(InflateError::UnsupportedDictionary);
+    }
+
+    // deflate can't do better than about 1032 to 1, so a short stream can't need all of `max_output`
+    let mut output = Vec::with_capacity(max_output.min(bytes.len().saturating_mul(1032)));
+    let length = inflate(&bytes[2..], &mut output, max_output)?;
+    let checksum = bytes
+        .get(2 + length..2 + length + 4)
+        .ok_or(InflateError::Truncated)?;
+    if u32::from_be_bytes(checksum.try_into().unwrap()) != adler32(&output) {
+        return Err(InflateError::ChecksumMismatch);
+    }
+    Ok(output)
+}
@@ -0,0 +1,404 @@
+use crate::{
+    framebuffer::{Color, Rgba},
+    image::{ImageError, Reader, check_size, deflate::ZlibEncoder, inflate::zlib_decompress},
+    screen::{RgbaPixels, Screen},
+};
+use alloc::{vec, vec::Vec};
+
+pub const SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', 0x0d, 0x0a, 0x1a, 0x0a];
+
+const COLOR_TYPE_GREYSCALE: u8 = 0;
+const COLOR_TYPE_RGB: u8 = 2;
+const COLOR_TYPE_PALETTE: u8 = 3;
+const COLOR_TYPE_GREYSCALE_ALPHA: u8 = 4;
+const COLOR_TYPE_RGBA: u8 = 6;
+
+// where each of the 7 adam7 passes starts and how far apart its pixels are, as (x, y, x step, y step)
+const ADAM7_PASSES: [(usize, usize, usize, usize); 7] = [
+    (0, 0, 8, 8),
+    (4, 0, 8, 8),
+    (0, 4, 4, 8),
+    (2, 0, 4, 4),
+    (0, 2, 2, 4),
+    (1, 0, 2, 2),
+    (0, 1, 1, 2),
+];
+const NOT_INTERLACED: [(usize, usize, usize, usize); 1] = [(0, 0, 1, 1)];
+
+// how much compressed data to gather before writing it out as an idat chunk
+const ENCODE_CHUNK_SIZE: usize = 32 * 1024;
+
+const CRC_TABLE: [u32; 256] = {
+    let mut table = [0; 256];
+    let mut index = 0;
+    while index < 256 {
+        let mut crc = index as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 == 1 {
+                0xedb88320 ^ (crc >> 1)
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[index] = crc;
+        index += 1;
+    }
+    table
+};
+
+// the crc of each chunk covers its type as well as its data
+pub fn crc32(parts: &[&[u8]]) -> u32 {
+    let mut crc = u32::MAX;
+    for part in parts {
+        for &byte in *part {
+            crc = CRC_TABLE[((crc ^ byte as u32) & 0xff) as usize] ^ (crc >> 8);
+        }
+    }
+    !crc
+}
+
+struct Header {
+    width: usize,
+    height: usize,
+    bit_depth: u8,
+    color_type: u8,
+    interlaced: bool,
+}
+
+impl Header {
+    fn channels(&self) -> usize {
+        match self.color_type {
+            COLOR_TYPE_GREYSCALE | COLOR_TYPE_PALETTE => 1,
+            COLOR_TYPE_GREYSCALE_ALPHA => 2,
+            COLOR_TYPE_RGB => 3,
+            _ => 4,
+        }
+    }
+
+    fn bits_per_pixel(&self) -> usize {
+        self.channels() * self.bit_depth as usize
+    }
+
+    // including the filter type byte at the start
+    fn row_length(&self, width: usize) -> usize {
+        1 + (width * self.bits_per_pixel()).div_ceil(8)
+    }
+
+    // the size of each pass, empty ones are left out of the data entirely
+    fn passes(&self) -> impl Iterator<Item = (usize, usize, usize, usize, usize, usize)> + '_ {
+        let passes: &[_] = if self.interlaced {
+            &ADAM7_PASSES
+        } else {
+            &NOT_INTERLACED
+        };
+        passes
+            .iter()
+            .map(|&(left, top, x_step, y_step)| {
+                let width = self.width.saturating_sub(left).div_ceil(x_step);
+                let height = self.height.saturating_sub(top).div_ceil(y_step);
+                (left, top, x_step, y_step, width, height)
+            })
+            .filter(|&(.., width, height)| width != 0 && height != 0)
+    }
+}
+
+fn parse_header(data: &[u8]) -> Result<Header, ImageError> {
+    let mut reader = Reader::new(data);
+    let width = reader.u32_be()? as usize;
+    let height = reader.u32_be()? as usize;
+    let bit_depth = reader.u8()?;
+    let color_type = reader.u8()?;
+    let compression = reader.u8()?;
+    let filter = reader.u8()?;
+    let interlace = reader.u8()?;
+
+    check_size(width, height)?;
+    let valid = match color_type {
+        COLOR_TYPE_GREYSCALE => matches!(bit_depth, 1 | 2 | 4 | 8 | 16),
+        COLOR_TYPE_PALETTE => matches!(bit_depth, 1 | 2 | 4 | 8),
+        COLOR_TYPE_RGB | COLOR_TYPE_GREYSCALE_ALPHA | COLOR_TYPE_RGBA => {
+            matches!(bit_depth, 8 | 16)
+        }
+        _ => false,
+    };
+    if !valid {
+        return Err(ImageError::UnsupportedColorType {
+            color_type,
+            bit_depth,
+        });
+    }
+    if compression != 0 {
+        return Err(ImageError::UnsupportedCompression(compression as u32));
+    }
+    if filter != 0 || interlace > 1 {
+        return Err(ImageError::InvalidHeader);
+    }
+
+    Ok(Header {
+        width,
+        height,
+        bit_depth,
+        color_type,
+        interlaced: interlace == 1,
+    })
+}
+
+fn paeth(left: u8, up: u8, up_left: u8) -> u8 {
+    let estimate = left as i16 + up as i16 - up_left as i16;
+    let distance_left = (estimate - left as i16).abs();
+    let distance_up = (estimate - up as i16).abs();
+    let distance_up_left = (estimate - up_left as i16).abs();
+    if distance_left <= distance_up && distance_left <= distance_up_left {
+        left
+    } else if distance_up <= distance_up_left {
+        up
+    } else {
+        up_left
+    }
+}
+
+// `previous` is the row above after it was unfiltered, all zeros for the first row of a pass
+fn unfilter(
+    filter: u8,
+    row: &mut [u8],
+    previous: &[u8],
+    bytes_per_pixel: usize,
+) -> Result<(), ImageError> {
+    match filter {
+        0 => {}
+        1 => {
+            for index in bytes_per_pixel..row.len() {
+                row[index] = row[index].wrapping_add(row[index - bytes_per_pixel]);
+            }
+        }
+        2 => {
+            for (byte, &up) in row.iter_mut().zip(previous) {
+                *byte = byte.wrapping_add(up);
+            }
+        }
+        3 => {
+            for index in 0..row.len() {
+                let left = index
+                    .checked_sub(bytes_per_pixel)
+                    .map_or(0, |index| row[index]);
+                let average = ((left as u16 + previous[index] as u16) / 2) as u8;
+                row[index] = row[index].wrapping_add(average);
+            }
+        }
+        4 => {
+            for index in 0..row.len() {
+                let (left, up_left) = index
+                    .checked_sub(bytes_per_pixel)
+                    .map_or((0, 0), |index| (row[index], previous[index]));
+                row[index] = row[index].wrapping_add(paeth(left, previous[index], up_left));
+            }
+        }
+        _ => return Err(ImageError::InvalidFilter(filter)),
+    }
+    Ok(())
+}
+
+// samples smaller than a byte are packed most significant first, 16 bit ones are big endian
+fn sample(row: &[u8], index: usize, bit_depth: u8) -> u16 {
+    match bit_depth {
+        16 => u16::from_be_bytes([row[index * 2], row[index * 2 + 1]]),
+        8 => row[index] as u16,
+        _ => {
+            let bit = index * bit_depth as usize;
+            let shift = 8 - bit_depth as usize - bit % 8;
+            ((row[bit / 8] >> shift) & ((1 << bit_depth) - 1)) as u16
+        }
+    }
+}
+
+fn to_u8(sample: u16, bit_depth: u8) -> u8 {
+    match bit_depth {
+        16 => (sample >> 8) as u8,
+        8 => sample as u8,
+        _ => (sample as u32 * 255 / ((1 << bit_depth) - 1)) as u8,
+    }
+}
+
+pub fn decode(bytes: &[u8]) -> Result<RgbaPixels, ImageError> {
+    let mut reader = Reader::new(bytes);
+    if reader.bytes(SIGNATURE.len())? != SIGNATURE {
+        return Err(ImageError::UnknownFormat);
+    }
+
+    let mut header = None;
+    let mut palette: &[u8] = &[];
+    let mut transparency: &[u8] = &[];
+    let mut compressed = Vec::new();
+    loop {
+        let length = reader.u32_be()? as usize;
+        let kind = reader.array::<4>()?;
+        let data = reader.bytes(length)?;
+        if reader.u32_be()? != crc32(&[&kind, data]) {
+            return Err(ImageError::ChecksumMismatch);
+        }
+
+        match &kind {
+            b"IHDR" => header = Some(parse_header(data)?),
+            // everything else has to come after the header
+            _ if header.is_none() => return Err(ImageError::InvalidHeader),
+            b"PLTE" => palette = data,
+            b"tRNS" => transparency = data,
+            b"IDAT" => compressed.extend_from_slice(data),
+            b"IEND" => break,
+            // lowercase first letter means the chunk can be safely ignored
+            _ if kind[0].is_ascii_lowercase() => {}
+            _ => return Err(ImageError::UnsupportedChunk(kind)),
+        }
+    }
+    let header = header.ok_or(ImageError::InvalidHeader)?;
+    if header.color_type == COLOR_TYPE_PALETTE && palette.is_empty() {
+        return Err(ImageError::MissingPalette);
+    }
+
+    let expected_length = header
+        .passes()
+        .map(|(.., width, height)| height * header.row_length(width))
+        .sum();
+    let mut data = zlib_decompress(&compressed, expected_length)?;
+    if data.len() != expected_length {
+        return Err(ImageError::Truncated);
+    }
+
+    // the transparent colour for the types without alpha, compared before scaling down to 8 bits
+    let transparent_sample = |index: usize| {
+        transparency
+            .get(index * 2..index * 2 + 2)
+            .map(|bytes| u16::from_be_bytes([bytes[0], bytes[1]]))
+    };
+    let transparent_grey = transparent_sample(0);
+    let transparent_rgb = (transparency.len() >= 6)
+        .then(|| [0, 1, 2].map(|index| transparent_sample(index).unwrap()));
+
+    let bit_depth = header.bit_depth;
+    let bytes_per_pixel = header.bits_per_pixel().div_ceil(8);
+    let mut pixels = vec![Rgba::TRANSPARENT; header.width * header.height];
+    let mut rows = data.as_mut_slice();
+    for (left, top, x_step, y_step, width, height) in header.passes() {
+        let row_length = header.row_length(width);
+        let mut previous = vec![0u8; row_length - 1];
+        for y in 0..height {
+            let (row, rest) = rows.split_at_mut(row_length);
+            rows = rest;
+            let (filter, row) = row.split_first_mut().unwrap();
+            unfilter(*filter, row, &previous, bytes_per_pixel)?;
+
+            for x in 0..width {
+                let value =
+                    |channel: usize| sample(row, x * header.channels() + channel, bit_depth);
+                let color = |r: u16, g: u16, b: u16| Color {
+                    r: to_u8(r, bit_depth),
+                    g: to_u8(g, bit_depth),
+                    b: to_u8(b, bit_depth),
+                };
+                let pixel = match header.color_type {
+                    COLOR_TYPE_GREYSCALE => {
+                        let grey = value(0);
+                        let alpha = if transparent_grey == Some(grey) {
+                            0
+                        } else {
+                            255
+                        };
+                        Rgba::new(color(grey, grey, grey), alpha)
+                    }
+                    COLOR_TYPE_RGB => {
+                        let rgb = [value(0), value(1), value(2)];
+                        let alpha = if transparent_rgb == Some(rgb) { 0 } else { 255 };
+                        Rgba::new(color(rgb[0], rgb[1], rgb[2]), alpha)
+                    }
+                    COLOR_TYPE_PALETTE => {
+                        let index = value(0) as usize;
+                        let entry = palette
+                            .get(index * 3..index * 3 + 3)
+                            .ok_or(ImageError::ColorIndexOutOfRange(index))?;
+                        let color = Color {
+                            r: entry[0],
+                            g: entry[1],
+                            b: entry[2],
+                        };
+                        Rgba::new(color, transparency.get(index).copied().unwrap_or(255))
+                    }
+                    COLOR_TYPE_GREYSCALE_ALPHA => {
+                        let grey = value(0);
+                        Rgba::new(color(grey, grey, grey), to_u8(value(1), bit_depth))
+                    }
+                    _ => Rgba::new(
+                        color(value(0), value(1), value(2)),
+                        to_u8(value(3), bit_depth),
+                    ),
+                };
+                pixels[left + x * x_step + (top + y * y_step) * header.width] = pixel;
+            }
+            previous.copy_from_slice(row);
+        }
+    }
+
+    Ok(RgbaPixels::from_vec(pixels, header.width, header.height))
+}
+
+fn write_chunk(write: &mut impl FnMut(&[u8]), kind: &[u8; 4], data: &[u8]) {
+    write(&(data.len() as u32).to_be_bytes());
+    write(kind);
+    write(data);
+    write(&crc32(&[kind, data]).to_be_bytes());
+}
+
+// 8 bit rgb, each row is filtered with whichever of none, sub or up leaves the smallest values
+pub fn encode<S: Screen + ?Sized>(screen: &S, write: &mut impl FnMut(&[u8])) {
+    let width = screen.width();
+    let height = screen.height();
+    let stride = width * 3;
+
+    write(&SIGNATURE);
+    let mut header = [0u8; 13];
+    header[0..4].copy_from_slice(&(width as u32).to_be_bytes());
+    header[4..8].copy_from_slice(&(height as u32).to_be_bytes());
+    header[8] = 8;
+    header[9] = COLOR_TYPE_RGB;
+    write_chunk(write, b"IHDR", &header);
+
+    let mut encoder = ZlibEncoder::new();
+    let mut previous = vec![0u8; stride];
+    let mut row = vec![0u8; stride];
+    let mut filtered = vec![0u8; 1 + stride];
+    for y in 0..height {
+        for x in 0..width {
+            let color = unsafe { screen.get_pixel_unchecked(x, y) };
+            row[x * 3..x * 3 + 3].copy_from_slice(&[color.r, color.g, color.b]);
+        }
+
+        let filter_byte = |filter: u8, index: usize| match filter {
+            1 => row[index].wrapping_sub(index.checked_sub(3).map_or(0, |index| row[index])),
+            2 => row[index].wrapping_sub(previous[index]),
+            _ => row[index],
+        };
+        // bytes as signed values, so small differences either way count as small
+        let filter = (0..=2)
+            .min_by_key(|&filter| {
+                (0..stride)
+                    .map(|index| (filter_byte(filter, index) as i8).unsigned_abs() as usize)
+                    .sum::<usize>()
+            })
+            .unwrap();
+
+        filtered[0] = filter;
+        for index in 0..stride {
+            filtered[1 + index] = filter_byte(filter, index);
+        }
+        encoder.write(&filtered);
+        if encoder.output().len() >= ENCODE_CHUNK_SIZE {
+            write_chunk(write, b"IDAT", encoder.output());
+            encoder.output().clear();
+        }
+        core::mem::swap(&mut previous, &mut row);
+    }
+
+    write_chunk(write, b"IDAT", &encoder.finish());
+    write_chunk(write, b"IEND", &[]);
+}
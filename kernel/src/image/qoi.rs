@@ -0,0 +1,116 @@
+use crate::{
+    framebuffer::{Color, Rgba},
+    image::{ImageError, Reader, check_size},
+    screen::RgbaPixels,
+};
+use alloc::vec::Vec;
+
+pub const MAGIC: &[u8] = b"qoif";
+
+const OP_RGB: u8 = 0xfe;
+const OP_RGBA: u8 = 0xff;
+const OP_INDEX: u8 = 0x00;
+const OP_DIFF: u8 = 0x40;
+const OP_LUMA: u8 = 0x80;
+const TAG_MASK: u8 = 0xc0;
+
+// qoi works on straight alpha, it's only premultiplied at the end
+#[derive(Clone, Copy, PartialEq, Eq)]
+struct Pixel {
+    r: u8,
+    g: u8,
+    b: u8,
+    a: u8,
+}
+
+impl Pixel {
+    fn hash(self) -> usize {
+        (self.r as usize * 3 + self.g as usize * 5 + self.b as usize * 7 + self.a as usize * 11)
+            % 64
+    }
+}
+
+pub fn decode(bytes: &[u8]) -> Result<RgbaPixels, ImageError> {
+    let mut reader = Reader::new(bytes);
+    if reader.bytes(MAGIC.len())? != MAGIC {
+        return Err(ImageError::UnknownFormat);
+    }
+    let width = reader.u32_be()? as usize;
+    let height = reader.u32_be()? as usize;
+    let channels = reader.u8()?;
+    let _color_space = reader.u8()?;
+    check_size(width, height)?;
+    if !matches!(channels, 3 | 4) {
+        return Err(ImageError::InvalidHeader);
+    }
+
+    let total = width * height;
+    let mut pixels = Vec::with_capacity(total);
+    let mut seen = [Pixel {
+        r: 0,
+        g: 0,
+        b: 0,
+        a: 0,
+    }; 64];
+    let mut pixel = Pixel {
+        r: 0,
+        g: 0,
+        b: 0,
+        a: 255,
+    };
+    while pixels.len() < total {
+        let op = reader.u8()?;
+        let mut run = 1;
+        match op {
+            OP_RGB => {
+                let [r, g, b] = reader.array()?;
+                pixel = Pixel { r, g, b, ..pixel };
+            }
+            OP_RGBA => {
+                let [r, g, b, a] = reader.array()?;
+                pixel = Pixel { r, g, b, a };
+            }
+            _ => match op & TAG_MASK {
+                OP_INDEX => pixel = seen[(op & 0x3f) as usize],
+                OP_DIFF => {
+                    pixel.r = pixel.r.wrapping_add((op >> 4) & 3).wrapping_sub(2);
+                    pixel.g = pixel.g.wrapping_add((op >> 2) & 3).wrapping_sub(2);
+                    pixel.b = pixel.b.wrapping_add(op & 3).wrapping_sub(2);
+                }
+                OP_LUMA => {
+                    let next = reader.u8()?;
+                    let green = (op & 0x3f).wrapping_sub(32);
+                    pixel.r = pixel
+                        .r
+                        .wrapping_add(green)
+                        .wrapping_add(next >> 4)
+                        .wrapping_sub(8);
+                    pixel.g = pixel.g.wrapping_add(green);
+                    pixel.b = pixel
+                        .b
+                        .wrapping_add(green)
+                        .wrapping_add(next & 0x0f)
+                        .wrapping_sub(8);
+                }
+                // a run, the two longest lengths are taken by `OP_RGB` and `OP_RGBA`
+                _ => run = (op & 0x3f) as usize + 1,
+            },
+        }
+        seen[pixel.hash()] = pixel;
+
+        if pixels.len() + run > total {
+            return Err(ImageError::CorruptData);
+        }
+        let color = Rgba::new(
+            Color {
+                r: pixel.r,
+                g: pixel.g,
+                b: pixel.b,
+            },
+            pixel.a,
+        );
+        pixels.extend((0..run).map(|_| color));
+    }
+
+    Ok(RgbaPixels::from_vec(pixels, width, height))
+}
@@ -0,0 +1,173 @@
+use crate::{
+    framebuffer::{Color, Rgba},
+    image::{ImageError, Reader, check_size},
+    screen::RgbaPixels,
+};
+use alloc::vec::Vec;
+
+const TYPE_COLOR_MAPPED: u8 = 1;
+const TYPE_TRUE_COLOR: u8 = 2;
+const TYPE_GREYSCALE: u8 = 3;
+// the same three, run length encoded
+const TYPE_RLE: u8 = 8;
+
+const DESCRIPTOR_ALPHA_BITS: u8 = 0x0f;
+const DESCRIPTOR_RIGHT_TO_LEFT: u8 = 0x10;
+const DESCRIPTOR_TOP_TO_BOTTOM: u8 = 0x20;
+
+// true colour pixels and colour map entries, which are stored little endian as bgr(a)
+fn true_color(bytes: &[u8], has_alpha: bool) -> Rgba {
+    match *bytes {
+        [low, high] => {
+            let value = u16::from_le_bytes([low, high]);
+            let channel = |shift: u16| (((value >> shift) & 0x1f) * 255 / 31) as u8;
+            let color = Color {
+                r: channel(10),
+                g: channel(5),
+                b: channel(0),
+            };
+            let alpha = if has_alpha && value & 0x8000 == 0 {
+                0
+            } else {
+                255
+            };
+            Rgba::new(color, alpha)
+        }
+        [b, g, r] => Rgba::opaque(Color { r, g, b }),
+        [b, g, r, a] => Rgba::new(Color { r, g, b }, if has_alpha { a } else { 255 }),
+        _ => unreachable!(),
+    }
+}
+
+// each packet is a header byte then either one pixel repeated or that many pixels as they are
+fn decode_rle(
+    reader: &mut Reader<'_>,
+    pixel_bytes: usize,
+    total: usize,
+) -> Result<Vec<u8>, ImageError> {
+    let mut data = Vec::with_capacity(total * pixel_bytes);
+    while data.len() < total * pixel_bytes {
+        let header = reader.u8()?;
+        let count = (header & 0x7f) as usize + 1;
+        if data.len() + count * pixel_bytes > total * pixel_bytes {
+            return Err(ImageError::CorruptData);
+        }
+        if header & 0x80 != 0 {
+            let pixel = reader.bytes(pixel_bytes)?;
+            for _ in 0..count {
+                data.extend_from_slice(pixel);
+            }
+        } else {
+            data.extend_from_slice(reader.bytes(count * pixel_bytes)?);
+        }
+    }
+    Ok(data)
+}
+
+pub fn decode(bytes: &[u8]) -> Result<RgbaPixels, ImageError> {
+    let mut reader = Reader::new(bytes);
+    let id_length = reader.u8()?;
+    let color_map_type = reader.u8()?;
+    let image_type = reader.u8()?;
+    let color_map_first = reader.u16_le()? as usize;
+    let color_map_length = reader.u16_le()? as usize;
+    let color_map_bits = reader.u8()?;
+    // the origin is only for where to put the image on a display
+    reader.skip(4)?;
+    let width = reader.u16_le()? as usize;
+    let height = reader.u16_le()? as usize;
+    let bits_per_pixel = reader.u8()?;
+    let descriptor = reader.u8()?;
+    reader.skip(id_length as usize)?;
+
+    let (kind, rle) = match image_type {
+        TYPE_COLOR_MAPPED | TYPE_TRUE_COLOR | TYPE_GREYSCALE => (image_type, false),
+        _ if matches!(
+            image_type.wrapping_sub(TYPE_RLE),
+            TYPE_COLOR_MAPPED | TYPE_TRUE_COLOR | TYPE_GREYSCALE
+        ) =>
+        {
+            (image_type - TYPE_RLE, true)
+        }
+        _ => return Err(ImageError::UnsupportedImageType(image_type)),
+    };
+    if color_map_type > 1 || (kind == TYPE_COLOR_MAPPED && color_map_type != 1) {
+        return Err(ImageError::InvalidHeader);
+    }
+    let supported = match kind {
+        TYPE_COLOR_MAPPED => matches!(bits_per_pixel, 8 | 16),
+        TYPE_TRUE_COLOR => matches!(bits_per_pixel, 15 | 16 | 24 | 32),
+        _ => matches!(bits_per_pixel, 8 | 16),
+    };
+    if !supported {
+        return Err(ImageError::UnsupportedBitsPerPixel(bits_per_pixel as u16));
+    }
+    check_size(width, height)?;
+    let has_alpha = descriptor & DESCRIPTOR_ALPHA_BITS != 0;
+
+    // a colour map can be there even when it isn't used, and has to be skipped over either way
+    let color_map_entry_bytes = (color_map_bits as usize).div_ceil(8);
+    let color_map = if color_map_type == 1 {
+        if !matches!(color_map_bits, 15 | 16 | 24 | 32) {
+            return Err(ImageError::UnsupportedBitsPerPixel(color_map_bits as u16));
+        }
+        reader.bytes(color_map_length * color_map_entry_bytes)?
+    } else {
+        &[]
+    };
+
+    let pixel_bytes = (bits_per_pixel as usize).div_ceil(8);
+    let total = width * height;
+    let decoded;
+    let data = if rle {
+        decoded = decode_rle(&mut reader, pixel_bytes, total)?;
+        &decoded[..]
+    } else {
+        reader.bytes(total * pixel_bytes)?
+    };
+
+    let mut pixels = Vec::with_capacity(total);
+    for pixel in data.chunks_exact(pixel_bytes) {
+        pixels.push(match kind {
+            TYPE_COLOR_MAPPED => {
+                let index = match *pixel {
+                    [index] => index as usize,
+                    [low, high] => u16::from_le_bytes([low, high]) as usize,
+                    _ => unreachable!(),
+                };
+                let entry = index
+                    .checked_sub(color_map_first)
+                    .and_then(|entry| {
+                        color_map
+                            .get(entry * color_map_entry_bytes..(entry + 1) * color_map_entry_bytes)
+                    })
+                    .ok_or(ImageError::ColorIndexOutOfRange(index))?;
+                true_color(entry, has_alpha)
+            }
+            TYPE_TRUE_COLOR => true_color(pixel, has_alpha),
+            _ => {
+                let grey = Color {
+                    r: pixel[0],
+                    g: pixel[0],
+                    b: pixel[0],
+                };
+                Rgba::new(grey, pixel.get(1).copied().unwrap_or(255))
+            }
+        });
+    }
+
+    // stored bottom to top and left to right unless the descriptor says otherwise
+    if descriptor & DESCRIPTOR_TOP_TO_BOTTOM == 0 {
+        for y in 0..height / 2 {
+            let (top, bottom) = pixels.split_at_mut((height - 1 - y) * width);
+            top[y * width..(y + 1) * width].swap_with_slice(&mut bottom[..width]);
+        }
+    }
+    if descriptor & DESCRIPTOR_RIGHT_TO_LEFT != 0 {
+        for row in pixels.chunks_exact_mut(width) {
+            row.reverse();
+        }
+    }
+
+    Ok(RgbaPixels::from_vec(pixels, width, height))
+}
@@ -0,0 +1,283 @@
+use crate::{
+    benchmark::run_benchmarks,
+    console::Console,
+    cpuid::{cpuid, is_cpuid_supported},
+    draw::{Draw, Point},
+    drivers::{
+        pic::{PIC1_DATA, PIC2_DATA, remap_pic},
+        ps2_keyboard::{KEYBOARD_STATE, Key, KeyState, keyboard_handler, setup_keyboard},
+        ps2_mouse::{MOUSE_STATE, MouseSensitivity, mouse_handler, setup_mouse},
+    },
+    framebuffer::{Color, framebuffer},
+    gdt::setup_gdt,
+    idt::{InterruptType, disable_interrupts, enable_interrupts, setup_idt, with_idt_entry},
+    page_allocator::{PAGE_SIZE, reclaim_boot_memory},
+    screen::{FramebufferColorPixels, Rect, Screen},
+    screenshot::{ScreenshotFormat, send_screenshot},
+    simd::{SimdLevel, enable_simd},
+    utils::{io_wait, outb},
+    view::{RotatedScreen, Rotation},
+};
+use alloc::{vec, vec::Vec};
+use boot_info::{
+    BootInfo,
+    kernel_args::{ArgError, KernelArgs},
+};
+use core::{cell::SyncUnsafeCell, fmt::Write, mem::MaybeUninit, str::FromStr};
+use font::SPACE_MONO;
+
+static BOOT_INFO: SyncUnsafeCell<Option<&'static BootInfo>> = SyncUnsafeCell::new(None);
+
+pub fn boot_info() -> &'static BootInfo {
+    unsafe { (*BOOT_INFO.get()).expect("the boot info should have been set") }
+}
+
+pub unsafe fn set_boot_info(boot_info: &'static BootInfo) {
+    unsafe { *BOOT_INFO.get() = Some(boot_info) };
+}
+
+pub fn kernel_args() -> KernelArgs<'static> {
+    boot_info().kernel_args()
+}
+
+// falls back to `default` if the argument is missing, or if it is invalid in which case it also gets recorded
+fn arg_or<T: FromStr>(key: &str, default: T, invalid_args: &mut Vec<ArgError<'static>>) -> T {
+    kernel_args()
+        .parse(key)
+        .unwrap_or_else(|error| {
+            invalid_args.push(error);
+            None
+        })
+        .unwrap_or(default)
+}
+
+pub fn kernel_main() -> ! {
+    unsafe { disable_interrupts() };
+
+    let framebuffer = framebuffer();
+    // the text without the cursor, so moving the cursor only has to restore what was under it
+    let mut text = FramebufferColorPixels::new(
+        Color { r: 0, g: 0, b: 0 },
+        framebuffer.width(),
+        framebuffer.height(),
+        framebuffer.layout(),
+    );
+    let mut pixels = FramebufferColorPixels::new(
+        Color { r: 0, g: 0, b: 0 },
+        framebuffer.width(),
+        framebuffer.height(),
+        framebuffer.layout(),
+    );
+
+    unsafe { setup_gdt() };
+    unsafe { setup_idt() };
+
+    // the firmware gdt and idt are in boot services memory, so this has to happen after replacing them
+    let reclaimed_pages = unsafe {
+        let memory_map = &boot_info().memory_map;
+        reclaim_boot_memory(memory_map, &[memory_map.buffer_range()])
+    };
+
+    unsafe { remap_pic(0x20, 0x28) };
+
+    unsafe {
+        with_idt_entry(0x21, |entry| {
+            entry.set_handler(keyboard_handler, InterruptType::Interrupt);
+        });
+    }
+    unsafe { setup_keyboard() };
+
+    unsafe {
+        with_idt_entry(0x2C, |entry| {
+            entry.set_handler(mouse_handler, InterruptType::Interrupt);
+        });
+    }
+    unsafe { setup_mouse() };
+
+    unsafe { outb::<PIC1_DATA>(0b11111001) };
+    io_wait();
+    unsafe { outb::<PIC2_DATA>(0b11101111) };
+    io_wait();
+
+    unsafe { enable_interrupts() };
+
+    assert!(is_cpuid_supported());
+
+    let mut cpu_name = [0u8; 12];
+    let (max_cpuid, cpu_name) = {
+        let result = unsafe { cpuid(0, MaybeUninit::uninit()) };
+        cpu_name[0..4].copy_from_slice(&result.ebx.to_ne_bytes());
+        cpu_name[4..8].copy_from_slice(&result.edx.to_ne_bytes());
+        cpu_name[8..12].copy_from_slice(&result.ecx.to_ne_bytes());
+        (
+            result.eax,
+            core::str::from_utf8(&cpu_name).expect("the cpu name should be valid utf8"),
+        )
+    };
+
+    let max_extended_cpuid = unsafe { cpuid(0x80000000, MaybeUninit::uninit()).eax };
+
+    let mut invalid_args = vec![];
+    let background = arg_or(
+        "background",
+        Color {
+            r: 50,
+            g: 50,
+            b: 50,
+        },
+        &mut invalid_args,
+    );
+    let text_color = arg_or(
+        "text_color",
+        Color {
+            r: 255,
+            g: 255,
+            b: 255,
+        },
+        &mut invalid_args,
+    );
+    let MouseSensitivity(mouse_sensitivity) =
+        arg_or("mouse.sensitivity", MouseSensitivity(1), &mut invalid_args);
+    let rotation = arg_or("rotation", Rotation::None, &mut invalid_args);
+    let (width, height) = rotation.rotated_size(framebuffer.width(), framebuffer.height());
+    let screenshot_format = arg_or("screenshot", ScreenshotFormat::Png, &mut invalid_args);
+
+    let simd_level = unsafe { enable_simd(arg_or("simd", SimdLevel::Avx2, &mut invalid_args)) };
+    let benchmarks = match kernel_args().flag("benchmark") {
+        Ok(true) => run_benchmarks(),
+        Ok(false) => vec![],
+        Err(error) => {
+            invalid_args.push(error);
+            vec![]
+        }
+    };
+
+    // an arrow with its tip at the mouse position
+    const CURSOR: [Point; 7] = [
+        (0, 0),
+        (0, 16),
+        (4, 12),
+        (7, 18),
+        (9, 17),
+        (6, 11),
+        (11, 11),
+    ];
+    const CURSOR_WIDTH: usize = 12;
+    const CURSOR_HEIGHT: usize = 19;
+
+    let scrollback = arg_or("console.scrollback", 1000usize, &mut invalid_args);
+    let mut console = Console::new(
+        &SPACE_MONO,
+        width,
+        height,
+        scrollback,
+        text_color,
+        background,
+    );
+    RotatedScreen::new(&mut text, rotation).fill(0, 0, width, height, background);
+
+    writeln!(console, "Max CPUID: {:#X}", max_cpuid).unwrap();
+    writeln!(console, "Max Extended CPUID: {:#X}", max_extended_cpuid).unwrap();
+    writeln!(console, "Cpu Name: {:?}", cpu_name).unwrap();
+    writeln!(
+        console,
+        "Reclaimed Boot Memory: {} KiB",
+        reclaimed_pages * PAGE_SIZE / 1024
+    )
+    .unwrap();
+    writeln!(console, "SIMD: {simd_level:?}").unwrap();
+    writeln!(console, "Command Line: {:?}", kernel_args().command_line()).unwrap();
+    for benchmark in &benchmarks {
+        writeln!(
+            console,
+            "Benchmark {} {:?}: {} cycles",
+            benchmark.name, benchmark.level, benchmark.cycles
+        )
+        .unwrap();
+    }
+    for error in &invalid_args {
+        writeln!(console, "\x1B[91mInvalid Kernel Argument: {error:?}\x1B[0m").unwrap();
+    }
+
+    let mut text_changed = true;
+    let mut take_screenshot = false;
+
+    let mut mouse_x = 0usize;
+    let mut mouse_y = 0usize;
+    loop {
+        let old_cursor = Rect::new(mouse_x, mouse_y, CURSOR_WIDTH, CURSOR_HEIGHT);
+
+        KEYBOARD_STATE.with(|keyboard| {
+            while let Some(event) = keyboard.next_event() {
+                text_changed = true;
+                let pressed = matches!(event.state, KeyState::Pressed);
+                // scrolling isn't written out, as that would jump straight back to the bottom
+                match event.key {
+                    Key::PageUp if pressed => console.page_up(),
+                    Key::PageDown if pressed => console.page_down(),
+                    Key::PageUp | Key::PageDown => {}
+                    _ => {
+                        if matches!(event.key, Key::PrintScreen) && pressed {
+                            take_screenshot = true;
+                        }
+                        writeln!(console, "{event:?}").unwrap();
+                    }
+                }
+            }
+        });
+
+        MOUSE_STATE.with(|mouse| {
+            while let Some(event) = mouse.next_event() {
+                mouse_x = mouse_x
+                    .saturating_add_signed(
+                        (event.x_offset as isize).saturating_mul(mouse_sensitivity),
+                    )
+                    .min(width - 1);
+                mouse_y = mouse_y
+                    .saturating_add_signed(
+                        (-(event.y_offset as isize)).saturating_mul(mouse_sensitivity),
+                    )
+                    .min(height - 1);
+            }
+        });
+
+        let cursor = Rect::new(mouse_x, mouse_y, CURSOR_WIDTH, CURSOR_HEIGHT);
+        if !text_changed && cursor == old_cursor {
+            continue;
+        }
+
+        if text_changed {
+            text_changed = false;
+            console.render(&mut RotatedScreen::new(&mut text, rotation));
+
+            let damage = text.damage().to_vec();
+            text.clear_damage();
+            for rect in damage {
+                pixels.copy_rect_from(&text, rect);
+            }
+        }
+
+        pixels.copy_rect_from(
+            &text,
+            rotation.map_rect(old_cursor, framebuffer.width(), framebuffer.height()),
+        );
+        let cursor_points = CURSOR.map(|(x, y)| (x + mouse_x as isize, y + mouse_y as isize));
+        let mut rotated_pixels = RotatedScreen::new(&mut pixels, rotation);
+        rotated_pixels.fill_polygon(
+            &cursor_points,
+            Color {
+                r: 255,
+                g: 255,
+                b: 255,
+            },
+        );
+        rotated_pixels.draw_polygon(&cursor_points, Color { r: 0, g: 0, b: 0 });
+        framebuffer.present(&mut pixels);
+
+        // after presenting, so it matches what's on the screen
+        if take_screenshot {
+            take_screenshot = false;
+            send_screenshot(&pixels, screenshot_format);
+        }
+    }
+}
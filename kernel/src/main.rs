@@ -0,0 +1,94 @@
+#![no_std]
+#![no_main]
+#![feature(
+    sync_unsafe_cell,
+    format_args_nl,
+    abi_x86_interrupt,
+    const_precise_live_drops
+)]
+
+use crate::{
+    drivers::serial::setup_serial,
+    framebuffer::{framebuffer, init_framebuffer},
+    idt::disable_interrupts,
+    kernel::{kernel_main, set_boot_info},
+    page_allocator::init_page_allocator,
+    screenshot::{ScreenshotFormat, send_screenshot},
+    utils::{error_screen, hlt},
+    virtual_memory::init_virtual_memory,
+};
+use boot_info::{BootInfo, KernelEntry};
+use core::fmt::Write;
+use core::panic::PanicInfo;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+pub mod ansi;
+pub mod benchmark;
+pub mod console;
+pub mod cpuid;
+pub mod dma;
+pub mod draw;
+pub mod drivers;
+pub mod framebuffer;
+pub mod gdt;
+pub mod heap;
+pub mod idt;
+pub mod image;
+pub mod interrupt_safe_mutex;
+pub mod kernel;
+pub mod page_allocator;
+pub mod rust_global_allocators;
+pub mod screen;
+pub mod screenshot;
+pub mod simd;
+pub mod text_writer;
+pub mod utils;
+pub mod view;
+pub mod virtual_memory;
+
+extern crate alloc;
+
+const _: KernelEntry = _start;
+
+// the bootloader has already exited boot services and switched to a stack made for us
+#[unsafe(no_mangle)]
+unsafe extern "sysv64" fn _start(boot_info: &'static BootInfo) -> ! {
+    unsafe { disable_interrupts() };
+
+    unsafe { init_framebuffer(&boot_info.framebuffer) };
+    // as early as possible, so even the earliest panics can send a screenshot
+    unsafe { setup_serial() };
+    assert_eq!(
+        boot_info.version,
+        BootInfo::VERSION,
+        "the kernel and bootloader should be from the same build"
+    );
+    unsafe { set_boot_info(boot_info) };
+
+    unsafe { init_page_allocator(&boot_info.memory_map) };
+    unsafe { init_virtual_memory(&boot_info.memory_map) };
+
+    kernel_main()
+}
+
+#[panic_handler]
+fn panic(info: &PanicInfo<'_>) -> ! {
+    error_screen(|text_writer| {
+        if let Some(location) = info.location() {
+            _ = write!(text_writer, "{}: ", location);
+        }
+        _ = writeln!(text_writer, "{}", info.message());
+    });
+
+    // always a bmp, since that is encoded without allocating and the panic might have come from
+    // inside the heap or page allocator with their locks still held
+    // a panic while sending it mustn't try again
+    static SENDING_SCREENSHOT: AtomicBool = AtomicBool::new(false);
+    if !SENDING_SCREENSHOT.swap(true, Ordering::Relaxed) {
+        send_screenshot(&framebuffer(), ScreenshotFormat::Bmp);
+    }
+
+    loop {
+        hlt();
+    }
+}
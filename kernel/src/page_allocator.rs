@@ -0,0 +1,522 @@
+use crate::{
+    hlt,
+    interrupt_safe_mutex::InterruptSafeMutex,
+    utils::error_screen,
+    virtual_memory::{Protection, VIRTUAL_MEMORY},
+};
+use core::{alloc::Layout, fmt::Write, ops::Range};
+
+pub const PAGE_SIZE: usize = 4096;
+
+// runs are 2^order pages long, so the largest run is 1GiB
+pub const MAX_ORDER: usize = 18;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Zone {
+    Dma16,
+    Dma32,
+    Normal,
+}
+
+impl Zone {
+    pub const ALL: [Self; 3] = [Self::Dma16, Self::Dma32, Self::Normal];
+
+    pub fn of(address: usize) -> Self {
+        if address < Self::Dma16.end_address() {
+            Self::Dma16
+        } else if address < Self::Dma32.end_address() {
+            Self::Dma32
+        } else {
+            Self::Normal
+        }
+    }
+
+    pub fn end_address(self) -> usize {
+        match self {
+            Self::Dma16 => 16 * 1024 * 1024,
+            Self::Dma32 => 4 * 1024 * 1024 * 1024,
+            Self::Normal => usize::MAX,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Block {
+    pub start_address: usize,
+    pub page_count: usize,
+    pub bitmap_start: usize,
+}
+
+impl Block {
+    pub fn end_address(&self) -> usize {
+        self.start_address + self.page_count * PAGE_SIZE
+    }
+
+    pub fn contains(&self, address: usize) -> bool {
+        address >= self.start_address && address < self.end_address()
+    }
+
+    fn index(&self, address: usize) -> usize {
+        self.bitmap_start + (address - self.start_address) / PAGE_SIZE
+    }
+}
+
+// stored in the first page of every free run
+struct FreeNode {
+    next: usize,
+    previous: usize,
+}
+
+fn node(address: usize) -> *mut FreeNode {
+    core::ptr::with_exposed_provenance_mut(address)
+}
+
+fn order_for(layout: Layout) -> usize {
+    let pages = layout.size().div_ceil(PAGE_SIZE);
+    let alignment_pages = layout.align().div_ceil(PAGE_SIZE);
+    pages.max(alignment_pages).next_power_of_two().ilog2() as usize
+}
+
+pub struct PageAllocator {
+    blocks: &'static [Block],
+    bitmap: &'static mut [u8],
+    // order + 1 for the first page of every free run, and 0 for every other page
+    orders: &'static mut [u8],
+    free_lists: [[usize; MAX_ORDER + 1]; Zone::ALL.len()],
+}
+
+impl PageAllocator {
+    pub fn blocks(&self) -> &'static [Block] {
+        self.blocks
+    }
+
+    fn block(&self, address: usize) -> Option<&'static Block> {
+        let blocks = self.blocks;
+        let index = blocks.partition_point(|block| block.end_address() <= address);
+        blocks.get(index).filter(|block| block.contains(address))
+    }
+
+    fn bit(&self, index: usize) -> bool {
+        self.bitmap[index / u8::BITS as usize] & (1 << (index % u8::BITS as usize)) != 0
+    }
+
+    fn set_bit(&mut self, index: usize, value: bool) {
+        let bitmap_index = index / u8::BITS as usize;
+        let bit_index = index % u8::BITS as usize;
+        if value {
+            self.bitmap[bitmap_index] |= 1 << bit_index;
+        } else {
+            self.bitmap[bitmap_index] &= !(1 << bit_index);
+        }
+    }
+
+    fn push(&mut self, block: &Block, address: usize, order: usize) {
+        let free_list = &mut self.free_lists[Zone::of(address) as usize][order];
+        let next = *free_list;
+        unsafe { node(address).write(FreeNode { next, previous: 0 }) };
+        if next != 0 {
+            unsafe { (*node(next)).previous = address };
+        }
+        *free_list = address;
+        self.orders[block.index(address)] = order as u8 + 1;
+    }
+
+    fn remove(&mut self, block: &Block, address: usize, order: usize) {
+        let FreeNode { next, previous } = unsafe { node(address).read() };
+        if previous != 0 {
+            unsafe { (*node(previous)).next = next };
+        } else {
+            self.free_lists[Zone::of(address) as usize][order] = next;
+        }
+        if next != 0 {
+            unsafe { (*node(next)).previous = previous };
+        }
+        self.orders[block.index(address)] = 0;
+    }
+
+    fn free_run(&mut self, block: &Block, mut address: usize, mut order: usize) {
+        while order < MAX_ORDER {
+            let buddy = address ^ (PAGE_SIZE << order);
+            // runs never cross into another zone, so each zone can have its own free lists
+            if !block.contains(buddy)
+                || Zone::of(buddy) != Zone::of(address)
+                || self.orders[block.index(buddy)] != order as u8 + 1
+            {
+                break;
+            }
+            self.remove(block, buddy, order);
+            address = address.min(buddy);
+            order += 1;
+        }
+        self.push(block, address, order);
+    }
+
+    // splits the range into the largest aligned runs it contains
+    fn free_range(&mut self, block: &Block, mut address: usize, pages: usize) {
+        let end = address + pages * PAGE_SIZE;
+        while address < end {
+            let order = ((address / PAGE_SIZE).trailing_zeros() as usize)
+                .min(((end - address) / PAGE_SIZE).ilog2() as usize)
+                .min(MAX_ORDER);
+            self.free_run(block, address, order);
+            address += PAGE_SIZE << order;
+        }
+    }
+
+    // takes a single free page out of whichever free run it is part of
+    fn claim_page(&mut self, block: &Block, address: usize) {
+        let mut order = 0;
+        let mut start = loop {
+            assert!(
+                order <= MAX_ORDER,
+                "a free page should be part of a free run"
+            );
+            let start = address & !((PAGE_SIZE << order) - 1);
+            if block.contains(start) && self.orders[block.index(start)] == order as u8 + 1 {
+                break start;
+            }
+            order += 1;
+        };
+
+        self.remove(block, start, order);
+        while order > 0 {
+            order -= 1;
+            let half = PAGE_SIZE << order;
+            if address >= start + half {
+                self.push(block, start, order);
+                start += half;
+            } else {
+                self.push(block, start + half, order);
+            }
+        }
+    }
+
+    pub unsafe fn set_allocated(&mut self, address: usize, value: bool) {
+        let Some(block) = self.block(address) else {
+            return;
+        };
+
+        let index = block.index(address);
+        if self.bit(index) == value {
+            return;
+        }
+        self.set_bit(index, value);
+
+        let address = address & !(PAGE_SIZE - 1);
+        if value {
+            self.claim_page(block, address);
+        } else {
+            self.free_run(block, address, 0);
+        }
+    }
+
+    pub fn get_allocated(&self, address: usize) -> Option<bool> {
+        let block = self.block(address)?;
+        Some(self.bit(block.index(address)))
+    }
+
+    pub fn allocate(&mut self, layout: Layout) -> Option<usize> {
+        self.allocate_in_zone(layout, Zone::Normal)
+    }
+
+    // allocates from the given zone or any zone below it, preferring the highest
+    // so the low zones are left for the devices that need them
+    pub fn allocate_in_zone(&mut self, layout: Layout, zone: Zone) -> Option<usize> {
+        if layout.size() == 0 {
+            return None;
+        }
+
+        let order = order_for(layout);
+        let (address, mut found_order) =
+            Zone::ALL[..=zone as usize].iter().rev().find_map(|&zone| {
+                let free_lists = &self.free_lists[zone as usize];
+                let found_order = (order..=MAX_ORDER).find(|&order| free_lists[order] != 0)?;
+                Some((free_lists[found_order], found_order))
+            })?;
+
+        let block = self
+            .block(address)
+            .expect("free runs should be inside a block");
+        self.remove(block, address, found_order);
+
+        while found_order > order {
+            found_order -= 1;
+            self.push(block, address + (PAGE_SIZE << found_order), found_order);
+        }
+
+        // give back the pages past the end of the allocation
+        let pages = layout.size().div_ceil(PAGE_SIZE);
+        self.free_range(block, address + pages * PAGE_SIZE, (1 << order) - pages);
+
+        for page in 0..pages {
+            self.set_bit(block.index(address) + page, true);
+        }
+        Some(address)
+    }
+
+    pub unsafe fn free(&mut self, address: usize, layout: Layout) {
+        let Some(block) = self.block(address) else {
+            return;
+        };
+
+        let pages = layout.size().div_ceil(PAGE_SIZE);
+        for page in 0..pages {
+            self.set_bit(block.index(address) + page, false);
+        }
+        self.free_range(block, address, pages);
+    }
+
+    pub unsafe fn resize_in_place(
+        &mut self,
+        address: usize,
+        layout: Layout,
+        new_size: usize,
+    ) -> bool {
+        let old_pages = layout.size().div_ceil(PAGE_SIZE);
+        let new_pages = new_size.div_ceil(PAGE_SIZE);
+
+        if new_pages > old_pages {
+            for i in old_pages..new_pages {
+                if self.get_allocated(address + i * PAGE_SIZE) != Some(false) {
+                    return false;
+                }
+            }
+            for i in old_pages..new_pages {
+                unsafe { self.set_allocated(address + i * PAGE_SIZE, true) };
+            }
+        } else if new_pages < old_pages {
+            let Some(block) = self.block(address) else {
+                return false;
+            };
+            let tail = address + new_pages * PAGE_SIZE;
+            for page in 0..old_pages - new_pages {
+                self.set_bit(block.index(tail) + page, false);
+            }
+            self.free_range(block, tail, old_pages - new_pages);
+        }
+        true
+    }
+
+    pub fn free_runs(&self, zone: Zone, order: usize) -> usize {
+        let mut count = 0;
+        let mut address = self.free_lists[zone as usize][order];
+        while address != 0 {
+            count += 1;
+            address = unsafe { (*node(address)).next };
+        }
+        count
+    }
+
+    pub fn total_pages(&self) -> usize {
+        self.blocks.iter().map(|block| block.page_count).sum()
+    }
+
+    pub fn allocated_pages(&self) -> usize {
+        self.bitmap
+            .iter()
+            .map(|byte| byte.count_ones() as usize)
+            .sum()
+    }
+
+    pub fn free_pages(&self) -> usize {
+        self.bitmap
+            .iter()
+            .map(|byte| byte.count_zeros() as usize)
+            .sum()
+    }
+}
+
+pub static PAGE_ALLOCATOR: InterruptSafeMutex<PageAllocator> =
+    InterruptSafeMutex::new(PageAllocator {
+        blocks: &[],
+        bitmap: &mut [],
+        orders: &mut [],
+        free_lists: [[0; _]; _],
+    });
+
+pub unsafe fn init_page_allocator(memory_map: &efi::MemoryMap) {
+    let mut required_page_bits = 0usize;
+    let mut block_count = 1usize;
+
+    for (i, memory_descriptor) in memory_map.iter().enumerate() {
+        if let Some(next_memory_descriptor) = memory_map.get(i + 1)
+            && memory_descriptor.physical_end() != next_memory_descriptor.physical_start
+        {
+            block_count += 1;
+        }
+
+        required_page_bits += memory_descriptor.number_of_pages;
+    }
+
+    let blocks_size = block_count * size_of::<Block>();
+    let bitmap_size = required_page_bits.div_ceil(u8::BITS as usize);
+    let orders_size = required_page_bits;
+    let required_allocator_size = blocks_size + bitmap_size + orders_size;
+
+    let mut start = None;
+    let mut size_so_far = 0;
+    for (i, memory_descriptor) in memory_map.iter().enumerate() {
+        // never put the state in the null page
+        let physical_start = memory_descriptor.physical_start.max(PAGE_SIZE);
+        let physical_end = memory_descriptor.physical_end();
+
+        let mut can_use_page = memory_descriptor.memory_type == efi::MemoryType::ConventionalMemory
+            && physical_end > physical_start;
+
+        if can_use_page {
+            start.get_or_insert(physical_start);
+            size_so_far += physical_end - physical_start;
+
+            if size_so_far >= required_allocator_size {
+                break;
+            }
+        }
+
+        if let Some(next_memory_descriptor) = memory_map.get(i + 1)
+            && physical_end != next_memory_descriptor.physical_start
+        {
+            can_use_page = false;
+        }
+
+        if !can_use_page {
+            start = None;
+            size_so_far = 0;
+        }
+    }
+    let Some(start) = start.filter(|_| size_so_far >= required_allocator_size) else {
+        error_screen(|writer| {
+            writeln!(
+                writer,
+                "Cannot find enough memory to store page allocator state"
+            )
+            .unwrap();
+
+            loop {
+                hlt();
+            }
+        })
+    };
+    let ptr = core::ptr::with_exposed_provenance_mut::<()>(start);
+
+    unsafe { core::ptr::write_bytes(ptr, 0, required_allocator_size) };
+    let blocks = unsafe { core::slice::from_raw_parts_mut(ptr.cast::<Block>(), block_count) };
+    let bitmap = unsafe {
+        core::slice::from_raw_parts_mut(ptr.byte_add(blocks_size).cast::<u8>(), bitmap_size)
+    };
+    let orders = unsafe {
+        core::slice::from_raw_parts_mut(
+            ptr.byte_add(blocks_size + bitmap_size).cast::<u8>(),
+            orders_size,
+        )
+    };
+
+    // everything starts out allocated, and usable memory is released below
+    bitmap.fill(!0);
+
+    {
+        let mut bitmap_index = 0;
+        let mut block_index = 0usize;
+        for (i, memory_descriptor) in memory_map.iter().enumerate() {
+            let block = &mut blocks[block_index];
+            if block.page_count == 0 {
+                block.start_address = memory_descriptor.physical_start;
+                block.bitmap_start = bitmap_index;
+            }
+
+            block.page_count += memory_descriptor.number_of_pages;
+            bitmap_index += memory_descriptor.number_of_pages;
+
+            if let Some(next_memory_descriptor) = memory_map.get(i + 1)
+                && memory_descriptor.physical_end() != next_memory_descriptor.physical_start
+            {
+                block_index += 1;
+            }
+        }
+    }
+
+    let mut page_allocator = PageAllocator {
+        blocks,
+        bitmap,
+        orders,
+        free_lists: [[0; _]; _],
+    };
+
+    for memory_descriptor in memory_map {
+        // the null page is never released since 0 marks the end of the free lists
+        let physical_start = memory_descriptor.physical_start.max(PAGE_SIZE);
+        let physical_end = memory_descriptor.physical_end();
+        if memory_descriptor.memory_type == efi::MemoryType::ConventionalMemory
+            && physical_end > physical_start
+        {
+            unsafe {
+                page_allocator.free(
+                    physical_start,
+                    Layout::from_size_align_unchecked(physical_end - physical_start, PAGE_SIZE),
+                );
+            }
+        }
+    }
+
+    for index in 0..required_allocator_size.div_ceil(PAGE_SIZE) {
+        unsafe { page_allocator.set_allocated(start + index * PAGE_SIZE, true) };
+    }
+
+    PAGE_ALLOCATOR.with(|alloc| *alloc = page_allocator);
+}
+
+pub unsafe fn reclaim_boot_memory(memory_map: &efi::MemoryMap, keep: &[Range<usize>]) -> usize {
+    let mut reclaimed_pages = 0;
+    for memory_descriptor in memory_map {
+        if !matches!(
+            memory_descriptor.memory_type,
+            efi::MemoryType::BootServicesCode
+                | efi::MemoryType::BootServicesData
+                | efi::MemoryType::LoaderCode
+                | efi::MemoryType::LoaderData
+        ) {
+            continue;
+        }
+
+        if matches!(
+            memory_descriptor.memory_type,
+            efi::MemoryType::BootServicesCode | efi::MemoryType::LoaderCode
+        ) {
+            // this is going to be used as normal memory now, so it shouldnt be executable anymore
+            // the null page was left unmapped
+            let physical_start = memory_descriptor.physical_start.max(PAGE_SIZE);
+            let physical_end = memory_descriptor.physical_end();
+            if physical_end > physical_start {
+                VIRTUAL_MEMORY.with(|vm| unsafe {
+                    vm.protect(
+                        physical_start,
+                        physical_end - physical_start,
+                        Protection::KERNEL_DATA,
+                    )
+                    .expect("all of the memory map should be mapped");
+                });
+            }
+        }
+
+        PAGE_ALLOCATOR.with(|alloc| {
+            for address in (memory_descriptor.physical_start..)
+                .step_by(PAGE_SIZE)
+                .take(memory_descriptor.number_of_pages)
+            {
+                // the null page is never released since 0 marks the end of the free lists
+                if address == 0
+                    || keep
+                        .iter()
+                        .any(|range| address + PAGE_SIZE > range.start && address < range.end)
+                {
+                    continue;
+                }
+
+                if alloc.get_allocated(address) == Some(true) {
+                    unsafe { alloc.set_allocated(address, false) };
+                    reclaimed_pages += 1;
+                }
+            }
+        });
+    }
+    reclaimed_pages
+}
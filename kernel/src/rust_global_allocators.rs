@@ -0,0 +1,29 @@
+use crate::heap::HEAP;
+use core::{alloc::GlobalAlloc, ptr::NonNull};
+
+#[derive(Debug, Clone, Copy)]
+struct GlobalAllocator;
+
+unsafe impl GlobalAlloc for GlobalAllocator {
+    unsafe fn alloc(&self, layout: core::alloc::Layout) -> *mut u8 {
+        HEAP.with(|heap| heap.allocate(layout))
+            .map_or(core::ptr::null_mut(), NonNull::as_ptr)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: core::alloc::Layout) {
+        HEAP.with(|heap| unsafe { heap.free(NonNull::new_unchecked(ptr), layout) })
+    }
+
+    unsafe fn realloc(
+        &self,
+        ptr: *mut u8,
+        layout: core::alloc::Layout,
+        new_size: usize,
+    ) -> *mut u8 {
+        HEAP.with(|heap| unsafe { heap.reallocate(NonNull::new_unchecked(ptr), layout, new_size) })
+            .map_or(core::ptr::null_mut(), NonNull::as_ptr)
+    }
+}
+
+#[global_allocator]
+static GLOBAL_ALLOCATOR: GlobalAllocator = GlobalAllocator;
@@ -0,0 +1,659 @@
+use crate::{
+    framebuffer::{BlendMode, Color, FramebufferColor, PixelLayout, Rgba},
+    simd::{blend_row, copy_row, fill_row},
+};
+use alloc::{vec, vec::Vec};
+use core::ops::Range;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rect {
+    pub left: usize,
+    pub top: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Rect {
+    pub const fn new(left: usize, top: usize, width: usize, height: usize) -> Self {
+        Self {
+            left,
+            top,
+            width,
+            height,
+        }
+    }
+
+    pub const fn right(&self) -> usize {
+        self.left.saturating_add(self.width)
+    }
+
+    pub const fn bottom(&self) -> usize {
+        self.top.saturating_add(self.height)
+    }
+
+    pub const fn area(&self) -> usize {
+        self.width * self.height
+    }
+
+    pub const fn is_empty(&self) -> bool {
+        self.width == 0 || self.height == 0
+    }
+
+    pub fn contains(&self, other: &Rect) -> bool {
+        other.left >= self.left
+            && other.top >= self.top
+            && other.right() <= self.right()
+            && other.bottom() <= self.bottom()
+    }
+
+    // the smallest rect that covers both
+    pub fn union(&self, other: &Rect) -> Rect {
+        if self.is_empty() {
+            return *other;
+        }
+        if other.is_empty() {
+            return *self;
+        }
+        let left = self.left.min(other.left);
+        let top = self.top.min(other.top);
+        Rect::new(
+            left,
+            top,
+            self.right().max(other.right()) - left,
+            self.bottom().max(other.bottom()) - top,
+        )
+    }
+
+    pub fn intersection(&self, other: &Rect) -> Rect {
+        let left = self.left.max(other.left);
+        let top = self.top.max(other.top);
+        let right = self.right().min(other.right());
+        let bottom = self.bottom().min(other.bottom());
+        Rect::new(
+            left,
+            top,
+            right.saturating_sub(left),
+            bottom.saturating_sub(top),
+        )
+    }
+}
+
+// a grid of alphas, like a glyph in a font page
+#[derive(Debug, Clone, Copy)]
+pub struct AlphaMask<'a> {
+    pub alphas: &'a [u8],
+    pub width: usize,
+    pub height: usize,
+    // how far apart rows are in `alphas`
+    pub stride: usize,
+}
+
+impl AlphaMask<'_> {
+    pub fn row(&self, y: usize) -> &[u8] {
+        &self.alphas[y * self.stride..y * self.stride + self.width]
+    }
+}
+
+pub trait Screen {
+    fn width(&self) -> usize;
+    fn height(&self) -> usize;
+    unsafe fn set_pixel_unchecked(&mut self, x: usize, y: usize, color: Color);
+    unsafe fn get_pixel_unchecked(&self, x: usize, y: usize) -> Color;
+
+    fn set_pixel(&mut self, x: usize, y: usize, color: Color) {
+        if x < self.width() && y < self.height() {
+            unsafe { self.set_pixel_unchecked(x, y, color) };
+        }
+    }
+
+    fn get_pixel(&self, x: usize, y: usize) -> Option<Color> {
+        if x < self.width() && y < self.height() {
+            Some(unsafe { self.get_pixel_unchecked(x, y) })
+        } else {
+            None
+        }
+    }
+
+    fn fill(&mut self, left: usize, top: usize, width: usize, height: usize, color: Color) {
+        let pixels_width = self.width();
+        let pixels_height = self.height();
+
+        let top = top.min(pixels_height);
+        let bottom = top.saturating_add(height).min(pixels_height);
+        let left = left.min(pixels_width);
+        let right = left.saturating_add(width).min(pixels_width);
+
+        for y in top..bottom {
+            for x in left..right {
+                unsafe { self.set_pixel_unchecked(x, y, color) };
+            }
+        }
+    }
+
+    fn copy(&mut self, screen: &dyn Screen, left: usize, top: usize) {
+        let width = self.width();
+        let height = self.height();
+        let screen_width = screen.width();
+        let screen_height = screen.height();
+
+        let top = top.min(height);
+        let bottom = top.saturating_add(screen_height).min(height);
+        let left = left.min(width);
+        let right = left.saturating_add(screen_width).min(width);
+
+        for y in top..bottom {
+            for x in left..right {
+                unsafe {
+                    self.set_pixel_unchecked(x, y, screen.get_pixel_unchecked(x - left, y - top));
+                }
+            }
+        }
+    }
+
+    // puts `color` over the existing pixels, using the mask as its alpha
+    fn blend_mask(&mut self, left: usize, top: usize, mask: AlphaMask<'_>, color: Color) {
+        let pixels_width = self.width();
+        let pixels_height = self.height();
+
+        let top = top.min(pixels_height);
+        let bottom = top.saturating_add(mask.height).min(pixels_height);
+        let left = left.min(pixels_width);
+        let right = left.saturating_add(mask.width).min(pixels_width);
+
+        for y in top..bottom {
+            let row = mask.row(y - top);
+            for x in left..right {
+                unsafe {
+                    let existing = self.get_pixel_unchecked(x, y);
+                    self.set_pixel_unchecked(x, y, existing.lerp(color, row[x - left]));
+                }
+            }
+        }
+    }
+
+    // a translucent rectangle over the existing pixels
+    fn fill_blended(&mut self, left: usize, top: usize, width: usize, height: usize, color: Rgba) {
+        let pixels_width = self.width();
+        let pixels_height = self.height();
+
+        let top = top.min(pixels_height);
+        let bottom = top.saturating_add(height).min(pixels_height);
+        let left = left.min(pixels_width);
+        let right = left.saturating_add(width).min(pixels_width);
+
+        for y in top..bottom {
+            for x in left..right {
+                unsafe {
+                    let existing = self.get_pixel_unchecked(x, y);
+                    self.set_pixel_unchecked(x, y, color.over(existing));
+                }
+            }
+        }
+    }
+
+    // screens are opaque, so the image always goes over what is there
+    fn composite(&mut self, image: &RgbaPixels, left: usize, top: usize) {
+        let pixels_width = self.width();
+        let pixels_height = self.height();
+
+        let top = top.min(pixels_height);
+        let bottom = top.saturating_add(image.height).min(pixels_height);
+        let left = left.min(pixels_width);
+        let right = left.saturating_add(image.width).min(pixels_width);
+
+        for y in top..bottom {
+            for x in left..right {
+                unsafe {
+                    let existing = self.get_pixel_unchecked(x, y);
+                    let color = *image
+                        .pixels
+                        .get_unchecked(x - left + (y - top) * image.width);
+                    self.set_pixel_unchecked(x, y, color.over(existing));
+                }
+            }
+        }
+    }
+}
+
+pub struct Pixels {
+    pixels: Vec<Color>,
+    width: usize,
+    height: usize,
+}
+
+impl Pixels {
+    pub const fn zero_size() -> Self {
+        Self {
+            pixels: vec![],
+            width: 0,
+            height: 0,
+        }
+    }
+
+    pub fn new(color: Color, width: usize, height: usize) -> Self {
+        Self {
+            pixels: vec![color; width * height],
+            width,
+            height,
+        }
+    }
+}
+
+impl Screen for Pixels {
+    fn width(&self) -> usize {
+        self.width
+    }
+
+    fn height(&self) -> usize {
+        self.height
+    }
+
+    unsafe fn set_pixel_unchecked(&mut self, x: usize, y: usize, color: Color) {
+        unsafe {
+            *self.pixels.get_unchecked_mut(x + y * self.width) = color;
+        }
+    }
+
+    unsafe fn get_pixel_unchecked(&self, x: usize, y: usize) -> Color {
+        unsafe { *self.pixels.get_unchecked(x + y * self.width) }
+    }
+}
+
+// past this many damaged rects new ones get merged into whichever existing one grows the least
+const MAX_DAMAGE_RECTS: usize = 16;
+// a rect that single pixels are added to can always grow up to this size, past it only along a row or column
+const PIXEL_DAMAGE_AREA: usize = 32 * 32;
+
+pub struct FramebufferColorPixels {
+    pixels: Vec<FramebufferColor>,
+    width: usize,
+    height: usize,
+    layout: PixelLayout,
+    damage: Vec<Rect>,
+    // lines and outlines are drawn a pixel at a time, so the rect the last one went into is tried first
+    // instead of searching all of them for every pixel
+    last_damaged: Option<usize>,
+}
+
+impl FramebufferColorPixels {
+    pub const fn zero_size(layout: PixelLayout) -> Self {
+        Self {
+            pixels: vec![],
+            width: 0,
+            height: 0,
+            layout,
+            damage: vec![],
+            last_damaged: None,
+        }
+    }
+
+    // everything starts out damaged, so the first present shows all of it
+    pub fn new(color: Color, width: usize, height: usize, layout: PixelLayout) -> Self {
+        Self {
+            pixels: vec![layout.encode(color); width * height],
+            width,
+            height,
+            layout,
+            damage: vec![Rect::new(0, 0, width, height)],
+            last_damaged: Some(0),
+        }
+    }
+
+    pub fn from_pixels(pixels: &Pixels, layout: PixelLayout) -> Self {
+        let mut encoded = vec![FramebufferColor(0); pixels.pixels.len()];
+        layout.encode_slice(&pixels.pixels, &mut encoded);
+        Self {
+            pixels: encoded,
+            width: pixels.width,
+            height: pixels.height,
+            layout,
+            damage: vec![Rect::new(0, 0, pixels.width, pixels.height)],
+            last_damaged: Some(0),
+        }
+    }
+
+    // the regions written since the last `clear_damage`
+    pub fn damage(&self) -> &[Rect] {
+        &self.damage
+    }
+
+    pub fn clear_damage(&mut self) {
+        self.damage.clear();
+        self.last_damaged = None;
+    }
+
+    pub fn mark_damaged(&mut self, rect: Rect) {
+        let rect = rect.intersection(&Rect::new(0, 0, self.width, self.height));
+        if rect.is_empty() {
+            return;
+        }
+        if let Some(index) = self.damage.iter().position(|damage| damage.contains(&rect)) {
+            self.last_damaged = Some(index);
+            return;
+        }
+
+        let growth = |damage: &Rect| {
+            damage.union(&rect).area() as isize - damage.area() as isize - rect.area() as isize
+        };
+        let full = self.damage.len() >= MAX_DAMAGE_RECTS;
+        let best = self
+            .damage
+            .iter_mut()
+            .enumerate()
+            .min_by_key(|(_, damage)| growth(damage));
+        match best {
+            // merging costs nothing if they overlap or sit right next to each other
+            Some((index, best)) if full || growth(best) <= 0 => {
+                *best = best.union(&rect);
+                self.last_damaged = Some(index);
+            }
+            _ => {
+                self.damage.push(rect);
+                self.last_damaged = Some(self.damage.len() - 1);
+            }
+        }
+    }
+
+    // grows the last damaged rect to cover the pixel if it is inside it or right next to it,
+    // as long as that doesn't cover much more than was drawn
+    fn mark_pixel_damaged(&mut self, x: usize, y: usize) {
+        let pixel = Rect::new(x, y, 1, 1);
+        if let Some(last) = self
+            .last_damaged
+            .and_then(|index| self.damage.get_mut(index))
+        {
+            let union = last.union(&pixel);
+            let touching = union.width <= last.width + 1 && union.height <= last.height + 1;
+            if touching
+                && (union.area() <= PIXEL_DAMAGE_AREA
+                    || union.area() - last.area() <= union.width.max(union.height))
+            {
+                *last = union;
+                return;
+            }
+        }
+        self.mark_damaged(pixel);
+    }
+
+    pub fn mark_all_damaged(&mut self) {
+        self.damage.clear();
+        self.damage.push(Rect::new(0, 0, self.width, self.height));
+        self.last_damaged = Some(0);
+    }
+
+    // copies straight from another buffer in the same layout, without converting any colors
+    pub fn copy_rect_from(&mut self, source: &FramebufferColorPixels, rect: Rect) {
+        assert_eq!(source.layout, self.layout);
+        let rect = rect
+            .intersection(&Rect::new(0, 0, self.width, self.height))
+            .intersection(&Rect::new(0, 0, source.width, source.height));
+        for y in rect.top..rect.bottom() {
+            let destination =
+                &mut self.pixels[rect.left + y * self.width..rect.right() + y * self.width];
+            let source =
+                &source.pixels[rect.left + y * source.width..rect.right() + y * source.width];
+            unsafe { copy_row(destination.as_mut_ptr(), source.as_ptr(), rect.width) };
+        }
+        self.mark_damaged(rect);
+    }
+
+    // decodes each row, lets `f` change it, and encodes it again
+    fn blend_rows(
+        &mut self,
+        columns: Range<usize>,
+        rows: Range<usize>,
+        mut f: impl FnMut(&mut [Color], usize),
+    ) {
+        let mut colors = vec![Color { r: 0, g: 0, b: 0 }; columns.len()];
+        for y in rows {
+            let row =
+                &mut self.pixels[columns.start + y * self.width..columns.end + y * self.width];
+            self.layout.decode_slice(row, &mut colors);
+            f(&mut colors, y);
+            self.layout.encode_slice(&colors, row);
+        }
+    }
+
+    pub fn layout(&self) -> PixelLayout {
+        self.layout
+    }
+
+    pub fn pixels(&self) -> *const FramebufferColor {
+        self.pixels.as_ptr()
+    }
+}
+
+impl Screen for FramebufferColorPixels {
+    fn width(&self) -> usize {
+        self.width
+    }
+
+    fn height(&self) -> usize {
+        self.height
+    }
+
+    unsafe fn set_pixel_unchecked(&mut self, x: usize, y: usize, color: Color) {
+        unsafe {
+            *self.pixels.get_unchecked_mut(x + y * self.width) = self.layout.encode(color);
+        }
+        self.mark_pixel_damaged(x, y);
+    }
+
+    unsafe fn get_pixel_unchecked(&self, x: usize, y: usize) -> Color {
+        unsafe {
+            self.layout
+                .decode(*self.pixels.get_unchecked(x + y * self.width))
+        }
+    }
+
+    fn fill(&mut self, left: usize, top: usize, width: usize, height: usize, color: Color) {
+        let top = top.min(self.height);
+        let bottom = top.saturating_add(height).min(self.height);
+        let left = left.min(self.width);
+        let right = left.saturating_add(width).min(self.width);
+
+        let color = self.layout.encode(color);
+        for y in top..bottom {
+            let row = &mut self.pixels[left + y * self.width..right + y * self.width];
+            unsafe { fill_row(row.as_mut_ptr(), right - left, color) };
+        }
+        self.mark_damaged(Rect::new(left, top, right - left, bottom - top));
+    }
+
+    fn copy(&mut self, screen: &dyn Screen, left: usize, top: usize) {
+        let top = top.min(self.height);
+        let bottom = top.saturating_add(screen.height()).min(self.height);
+        let left = left.min(self.width);
+        let right = left.saturating_add(screen.width()).min(self.width);
+
+        // read a whole row first so it can be converted in one go
+        let mut row = vec![Color { r: 0, g: 0, b: 0 }; right - left];
+        for y in top..bottom {
+            for (x, color) in (left..right).zip(&mut row) {
+                *color = unsafe { screen.get_pixel_unchecked(x - left, y - top) };
+            }
+            self.layout.encode_slice(
+                &row,
+                &mut self.pixels[left + y * self.width..right + y * self.width],
+            );
+        }
+        self.mark_damaged(Rect::new(left, top, right - left, bottom - top));
+    }
+
+    fn blend_mask(&mut self, left: usize, top: usize, mask: AlphaMask<'_>, color: Color) {
+        let top = top.min(self.height);
+        let bottom = top.saturating_add(mask.height).min(self.height);
+        let left = left.min(self.width);
+        let right = left.saturating_add(mask.width).min(self.width);
+
+        if self.layout.is_byte_aligned() {
+            let color = self.layout.encode(color);
+            for y in top..bottom {
+                let alphas = &mask.row(y - top)[..right - left];
+                let row = &mut self.pixels[left + y * self.width..right + y * self.width];
+                unsafe { blend_row(row.as_mut_ptr(), alphas, color) };
+            }
+        } else {
+            self.blend_rows(left..right, top..bottom, |colors, y| {
+                for (existing, &alpha) in colors.iter_mut().zip(mask.row(y - top)) {
+                    *existing = existing.lerp(color, alpha);
+                }
+            });
+        }
+        self.mark_damaged(Rect::new(left, top, right - left, bottom - top));
+    }
+
+    fn fill_blended(&mut self, left: usize, top: usize, width: usize, height: usize, color: Rgba) {
+        let top = top.min(self.height);
+        let bottom = top.saturating_add(height).min(self.height);
+        let left = left.min(self.width);
+        let right = left.saturating_add(width).min(self.width);
+
+        self.blend_rows(left..right, top..bottom, |colors, _| {
+            for existing in colors {
+                *existing = color.over(*existing);
+            }
+        });
+        self.mark_damaged(Rect::new(left, top, right - left, bottom - top));
+    }
+
+    fn composite(&mut self, image: &RgbaPixels, left: usize, top: usize) {
+        let top = top.min(self.height);
+        let bottom = top.saturating_add(image.height).min(self.height);
+        let left = left.min(self.width);
+        let right = left.saturating_add(image.width).min(self.width);
+
+        self.blend_rows(left..right, top..bottom, |colors, y| {
+            let start = (y - top) * image.width;
+            for (existing, color) in colors.iter_mut().zip(&image.pixels[start..]) {
+                *existing = color.over(*existing);
+            }
+        });
+        self.mark_damaged(Rect::new(left, top, right - left, bottom - top));
+    }
+}
+
+// an image with its own alpha, drawing to it through `Screen` makes opaque pixels
+pub struct RgbaPixels {
+    pixels: Vec<Rgba>,
+    width: usize,
+    height: usize,
+}
+
+impl RgbaPixels {
+    pub const fn zero_size() -> Self {
+        Self {
+            pixels: vec![],
+            width: 0,
+            height: 0,
+        }
+    }
+
+    pub fn new(color: Rgba, width: usize, height: usize) -> Self {
+        Self {
+            pixels: vec![color; width * height],
+            width,
+            height,
+        }
+    }
+
+    pub fn from_vec(pixels: Vec<Rgba>, width: usize, height: usize) -> Self {
+        assert_eq!(pixels.len(), width * height);
+        Self {
+            pixels,
+            width,
+            height,
+        }
+    }
+
+    // drops the alpha by putting everything over `background`
+    pub fn flatten(&self, background: Color) -> Pixels {
+        Pixels {
+            pixels: self
+                .pixels
+                .iter()
+                .map(|pixel| pixel.over(background))
+                .collect(),
+            width: self.width,
+            height: self.height,
+        }
+    }
+
+    pub fn get_rgba(&self, x: usize, y: usize) -> Option<Rgba> {
+        (x < self.width && y < self.height).then(|| self.pixels[x + y * self.width])
+    }
+
+    pub fn set_rgba(&mut self, x: usize, y: usize, color: Rgba) {
+        if x < self.width && y < self.height {
+            self.pixels[x + y * self.width] = color;
+        }
+    }
+
+    // porter-duff with `image` as the source and these pixels as the destination
+    pub fn composite_with(&mut self, image: &RgbaPixels, left: usize, top: usize, mode: BlendMode) {
+        let top = top.min(self.height);
+        let bottom = top.saturating_add(image.height).min(self.height);
+        let left = left.min(self.width);
+        let right = left.saturating_add(image.width).min(self.width);
+
+        for y in top..bottom {
+            let source = &image.pixels[(y - top) * image.width..];
+            let destination = &mut self.pixels[left + y * self.width..right + y * self.width];
+            for (destination, source) in destination.iter_mut().zip(source) {
+                *destination = source.blend(*destination, mode);
+            }
+        }
+    }
+}
+
+impl Screen for RgbaPixels {
+    fn width(&self) -> usize {
+        self.width
+    }
+
+    fn height(&self) -> usize {
+        self.height
+    }
+
+    unsafe fn set_pixel_unchecked(&mut self, x: usize, y: usize, color: Color) {
+        unsafe {
+            *self.pixels.get_unchecked_mut(x + y * self.width) = Rgba::opaque(color);
+        }
+    }
+
+    unsafe fn get_pixel_unchecked(&self, x: usize, y: usize) -> Color {
+        unsafe { self.pixels.get_unchecked(x + y * self.width).color() }
+    }
+
+    // these keep the alpha, so text and shapes can be drawn onto a transparent layer
+    fn blend_mask(&mut self, left: usize, top: usize, mask: AlphaMask<'_>, color: Color) {
+        let top = top.min(self.height);
+        let bottom = top.saturating_add(mask.height).min(self.height);
+        let left = left.min(self.width);
+        let right = left.saturating_add(mask.width).min(self.width);
+
+        for y in top..bottom {
+            let row = &mut self.pixels[left + y * self.width..right + y * self.width];
+            for (existing, &alpha) in row.iter_mut().zip(mask.row(y - top)) {
+                *existing = Rgba::new(color, alpha).blend(*existing, BlendMode::Over);
+            }
+        }
+    }
+
+    fn fill_blended(&mut self, left: usize, top: usize, width: usize, height: usize, color: Rgba) {
+        let top = top.min(self.height);
+        let bottom = top.saturating_add(height).min(self.height);
+        let left = left.min(self.width);
+        let right = left.saturating_add(width).min(self.width);
+
+        for y in top..bottom {
+            for existing in &mut self.pixels[left + y * self.width..right + y * self.width] {
+                *existing = color.blend(*existing, BlendMode::Over);
+            }
+        }
+    }
+
+    fn composite(&mut self, image: &RgbaPixels, left: usize, top: usize) {
+        self.composite_with(image, left, top, BlendMode::Over);
+    }
+}
@@ -0,0 +1,114 @@
+use crate::{
+    drivers::serial::write_serial,
+    image::{bmp, png},
+    screen::Screen,
+};
+use core::str::FromStr;
+
+// set with `screenshot=` on the command line
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScreenshotFormat {
+    Bmp,
+    Png,
+}
+
+impl FromStr for ScreenshotFormat {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "bmp" => Ok(Self::Bmp),
+            "png" => Ok(Self::Png),
+            _ => Err(()),
+        }
+    }
+}
+
+impl ScreenshotFormat {
+    pub const fn extension(self) -> &'static str {
+        match self {
+            Self::Bmp => "bmp",
+            Self::Png => "png",
+        }
+    }
+}
+
+pub fn encode_screenshot<S: Screen + ?Sized>(
+    screen: &S,
+    format: ScreenshotFormat,
+    write: &mut impl FnMut(&[u8]),
+) {
+    match format {
+        ScreenshotFormat::Bmp => bmp::encode(screen, write),
+        ScreenshotFormat::Png => png::encode(screen, write),
+    }
+}
+
+// base64 in lines of 76 characters, like pem, so it gets through a terminal untouched
+struct Base64Lines {
+    pending: [u8; 3],
+    pending_length: usize,
+    column: usize,
+}
+
+impl Base64Lines {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    const LINE_LENGTH: usize = 76;
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.pending[self.pending_length] = byte;
+            self.pending_length += 1;
+            if self.pending_length == 3 {
+                self.flush_group();
+            }
+        }
+    }
+
+    // a partial group at the end is padded with `=`
+    fn flush_group(&mut self) {
+        if self.pending_length == 0 {
+            return;
+        }
+        let [a, b, c] = self.pending;
+        let group = (a as u32) << 16 | (b as u32) << 8 | c as u32;
+        let mut characters = [b'='; 4];
+        for (index, character) in characters[..self.pending_length + 1].iter_mut().enumerate() {
+            *character = Self::ALPHABET[(group >> (18 - index * 6)) as usize & 0x3F];
+        }
+        write_serial(&characters);
+        self.pending = [0; 3];
+        self.pending_length = 0;
+
+        self.column += 4;
+        if self.column == Self::LINE_LENGTH {
+            write_serial(b"\n");
+            self.column = 0;
+        }
+    }
+
+    fn finish(mut self) {
+        self.flush_group();
+        if self.column != 0 {
+            write_serial(b"\n");
+        }
+    }
+}
+
+// the image can be pulled out of a serial log with
+// `sed -n '/BEGIN SCREENSHOT/,/END SCREENSHOT/{//!p}' serial.log | base64 -d > screenshot.png`
+pub fn send_screenshot<S: Screen + ?Sized>(screen: &S, format: ScreenshotFormat) {
+    write_serial(b"\n-----BEGIN SCREENSHOT ");
+    write_serial(format.extension().as_bytes());
+    write_serial(b"-----\n");
+
+    let mut lines = Base64Lines {
+        pending: [0; 3],
+        pending_length: 0,
+        column: 0,
+    };
+    encode_screenshot(screen, format, &mut |bytes| lines.write(bytes));
+    lines.finish();
+
+    write_serial(b"-----END SCREENSHOT-----\n");
+}
@@ -0,0 +1,457 @@
+use crate::{cpuid::cpuid, framebuffer::FramebufferColor};
+use core::{
+    arch::asm,
+    mem::MaybeUninit,
+    str::FromStr,
+    sync::atomic::{AtomicU8, Ordering},
+};
+
+// the kernel is built without sse, so the compiler never uses the vector registers and the intrinsics
+// would just be emulated, the fast paths are inline asm instead
+// nothing else touches the vector registers, which is why interrupt handlers dont have to save them
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[repr(u8)]
+pub enum SimdLevel {
+    Scalar,
+    Sse2,
+    Avx2,
+}
+
+impl SimdLevel {
+    pub const ALL: [SimdLevel; 3] = [SimdLevel::Scalar, SimdLevel::Sse2, SimdLevel::Avx2];
+
+    const fn from_u8(value: u8) -> Self {
+        match value {
+            1 => SimdLevel::Sse2,
+            2 => SimdLevel::Avx2,
+            _ => SimdLevel::Scalar,
+        }
+    }
+}
+
+// the highest level to use, set with `simd=` on the command line
+impl FromStr for SimdLevel {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "off" | "scalar" => Ok(SimdLevel::Scalar),
+            "sse2" => Ok(SimdLevel::Sse2),
+            "avx2" => Ok(SimdLevel::Avx2),
+            _ => Err(()),
+        }
+    }
+}
+
+const CR0_EMULATION: usize = 1 << 2;
+const CR0_MONITOR_COPROCESSOR: usize = 1 << 1;
+const CR4_OSFXSR: usize = 1 << 9;
+const CR4_OSXMMEXCPT: usize = 1 << 10;
+const CR4_OSXSAVE: usize = 1 << 18;
+const XCR0_X87: u64 = 1 << 0;
+const XCR0_SSE: u64 = 1 << 1;
+const XCR0_AVX: u64 = 1 << 2;
+
+// what `enable_simd` turned on, the level in use can be lowered but never raised past this
+static SUPPORTED_SIMD_LEVEL: AtomicU8 = AtomicU8::new(SimdLevel::Scalar as u8);
+static SIMD_LEVEL: AtomicU8 = AtomicU8::new(SimdLevel::Scalar as u8);
+
+pub fn detect_simd_level() -> SimdLevel {
+    let max_cpuid = unsafe { cpuid(0, MaybeUninit::uninit()).eax };
+    let features = unsafe { cpuid(1, MaybeUninit::uninit()) };
+    let sse2 = features.edx & (1 << 26) != 0;
+    let xsave = features.ecx & (1 << 26) != 0;
+    let avx = features.ecx & (1 << 28) != 0;
+    let avx2 = max_cpuid >= 7 && unsafe { cpuid(7, MaybeUninit::new(0)).ebx } & (1 << 5) != 0;
+
+    if sse2 && xsave && avx && avx2 {
+        SimdLevel::Avx2
+    } else if sse2 {
+        SimdLevel::Sse2
+    } else {
+        SimdLevel::Scalar
+    }
+}
+
+// turns on everything the cpu supports up to `max_level`, returns the level that ended up in use
+pub unsafe fn enable_simd(max_level: SimdLevel) -> SimdLevel {
+    let level = detect_simd_level().min(max_level);
+
+    if level >= SimdLevel::Sse2 {
+        unsafe {
+            let cr0: usize;
+            asm!("mov {}, cr0", out(reg) cr0, options(nomem, nostack));
+            let cr0 = cr0 & !CR0_EMULATION | CR0_MONITOR_COPROCESSOR;
+            asm!("mov cr0, {}", in(reg) cr0, options(nostack));
+
+            let cr4: usize;
+            asm!("mov {}, cr4", out(reg) cr4, options(nomem, nostack));
+            let mut cr4 = cr4 | CR4_OSFXSR | CR4_OSXMMEXCPT;
+            if level >= SimdLevel::Avx2 {
+                cr4 |= CR4_OSXSAVE;
+            }
+            asm!("mov cr4, {}", in(reg) cr4, options(nostack));
+        }
+    }
+
+    if level >= SimdLevel::Avx2 {
+        let xcr0 = XCR0_X87 | XCR0_SSE | XCR0_AVX;
+        unsafe {
+            asm!(
+                "xsetbv",
+                in("ecx") 0,
+                in("eax") xcr0 as u32,
+                in("edx") (xcr0 >> 32) as u32,
+                options(nomem, nostack)
+            );
+        }
+    }
+
+    SUPPORTED_SIMD_LEVEL.store(level as u8, Ordering::Relaxed);
+    SIMD_LEVEL.store(level as u8, Ordering::Relaxed);
+    level
+}
+
+pub fn supported_simd_level() -> SimdLevel {
+    SimdLevel::from_u8(SUPPORTED_SIMD_LEVEL.load(Ordering::Relaxed))
+}
+
+pub fn simd_level() -> SimdLevel {
+    SimdLevel::from_u8(SIMD_LEVEL.load(Ordering::Relaxed))
+}
+
+// only for comparing the paths against each other, the level cant go above what was enabled
+pub fn set_simd_level(level: SimdLevel) {
+    assert!(level <= supported_simd_level());
+    SIMD_LEVEL.store(level as u8, Ordering::Relaxed);
+}
+
+// these take raw pointers so they also work on the framebuffer itself
+
+pub unsafe fn fill_row(row: *mut FramebufferColor, length: usize, color: FramebufferColor) {
+    match simd_level() {
+        SimdLevel::Scalar => unsafe { fill_row_scalar(row, length, color) },
+        SimdLevel::Sse2 => unsafe { fill_row_sse2(row, length, color) },
+        SimdLevel::Avx2 => unsafe { fill_row_avx2(row, length, color) },
+    }
+}
+
+pub unsafe fn copy_row(
+    destination: *mut FramebufferColor,
+    source: *const FramebufferColor,
+    length: usize,
+) {
+    match simd_level() {
+        SimdLevel::Scalar => unsafe { copy_row_scalar(destination, source, length) },
+        SimdLevel::Sse2 => unsafe { copy_row_sse2(destination, source, length) },
+        SimdLevel::Avx2 => unsafe { copy_row_avx2(destination, source, length) },
+    }
+}
+
+// lerps every byte already in the row towards `foreground` by the matching alpha
+// so it only gives the same result as `Color::lerp` for layouts with 8 bit channels on byte boundaries
+pub unsafe fn blend_row(row: *mut FramebufferColor, alphas: &[u8], foreground: FramebufferColor) {
+    match simd_level() {
+        SimdLevel::Scalar => unsafe { blend_row_scalar(row, alphas, foreground) },
+        SimdLevel::Sse2 => unsafe { blend_row_sse2(row, alphas, foreground) },
+        SimdLevel::Avx2 => unsafe { blend_row_avx2(row, alphas, foreground) },
+    }
+}
+
+unsafe fn fill_row_scalar(row: *mut FramebufferColor, length: usize, color: FramebufferColor) {
+    for x in 0..length {
+        unsafe { row.add(x).write(color) };
+    }
+}
+
+unsafe fn fill_row_sse2(row: *mut FramebufferColor, length: usize, color: FramebufferColor) {
+    let vectors = length / 4;
+    if vectors != 0 {
+        unsafe {
+            asm!(
+                "movd xmm0, {color:e}",
+                "pshufd xmm0, xmm0, 0",
+                "2:",
+                "movdqu [{row}], xmm0",
+                "add {row}, 16",
+                "dec {vectors}",
+                "jnz 2b",
+                row = inout(reg) row => _,
+                vectors = inout(reg) vectors => _,
+                color = in(reg) color.0,
+                out("xmm0") _,
+                options(nostack)
+            );
+        }
+    }
+    unsafe { fill_row_scalar(row.add(vectors * 4), length % 4, color) };
+}
+
+unsafe fn fill_row_avx2(row: *mut FramebufferColor, length: usize, color: FramebufferColor) {
+    let vectors = length / 8;
+    if vectors != 0 {
+        unsafe {
+            asm!(
+                "vmovd xmm0, {color:e}",
+                "vpbroadcastd ymm0, xmm0",
+                "2:",
+                "vmovdqu [{row}], ymm0",
+                "add {row}, 32",
+                "dec {vectors}",
+                "jnz 2b",
+                "vzeroupper",
+                row = inout(reg) row => _,
+                vectors = inout(reg) vectors => _,
+                color = in(reg) color.0,
+                out("xmm0") _,
+                options(nostack)
+            );
+        }
+    }
+    unsafe { fill_row_scalar(row.add(vectors * 8), length % 8, color) };
+}
+
+unsafe fn copy_row_scalar(
+    destination: *mut FramebufferColor,
+    source: *const FramebufferColor,
+    length: usize,
+) {
+    for x in 0..length {
+        unsafe { destination.add(x).write(source.add(x).read()) };
+    }
+}
+
+unsafe fn copy_row_sse2(
+    destination: *mut FramebufferColor,
+    source: *const FramebufferColor,
+    length: usize,
+) {
+    let vectors = length / 4;
+    if vectors != 0 {
+        unsafe {
+            asm!(
+                "2:",
+                "movdqu xmm0, [{source}]",
+                "movdqu [{destination}], xmm0",
+                "add {source}, 16",
+                "add {destination}, 16",
+                "dec {vectors}",
+                "jnz 2b",
+                destination = inout(reg) destination => _,
+                source = inout(reg) source => _,
+                vectors = inout(reg) vectors => _,
+                out("xmm0") _,
+                options(nostack)
+            );
+        }
+    }
+    let done = vectors * 4;
+    unsafe { copy_row_scalar(destination.add(done), source.add(done), length % 4) };
+}
+
+unsafe fn copy_row_avx2(
+    destination: *mut FramebufferColor,
+    source: *const FramebufferColor,
+    length: usize,
+) {
+    let vectors = length / 8;
+    if vectors != 0 {
+        unsafe {
+            asm!(
+                "2:",
+                "vmovdqu ymm0, [{source}]",
+                "vmovdqu [{destination}], ymm0",
+                "add {source}, 32",
+                "add {destination}, 32",
+                "dec {vectors}",
+                "jnz 2b",
+                "vzeroupper",
+                destination = inout(reg) destination => _,
+                source = inout(reg) source => _,
+                vectors = inout(reg) vectors => _,
+                out("xmm0") _,
+                options(nostack)
+            );
+        }
+    }
+    let done = vectors * 8;
+    unsafe { copy_row_scalar(destination.add(done), source.add(done), length % 8) };
+}
+
+// same rounding as `Color::lerp`, each side is x * y / 255 rounded down
+const fn blend_pixel(background: u32, foreground: u32, alpha: u8) -> u32 {
+    let mut result = 0;
+    let mut shift = 0;
+    while shift < 32 {
+        let background = (background >> shift) & 0xFF;
+        let foreground = (foreground >> shift) & 0xFF;
+        let alpha = alpha as u32;
+        let value = background * (255 - alpha) / 255 + foreground * alpha / 255;
+        result |= value << shift;
+        shift += 8;
+    }
+    result
+}
+
+unsafe fn blend_row_scalar(
+    row: *mut FramebufferColor,
+    alphas: &[u8],
+    foreground: FramebufferColor,
+) {
+    for (x, &alpha) in alphas.iter().enumerate() {
+        unsafe {
+            let pixel = row.add(x);
+            pixel.write(FramebufferColor(blend_pixel(
+                pixel.read().0,
+                foreground.0,
+                alpha,
+            )));
+        }
+    }
+}
+
+// the vector versions work on 16 bit lanes, x / 255 for any 16 bit x is (x * 0x8081) >> 23
+// xmm7 is zero, xmm6 is 255 in every lane, xmm4 is the foreground bytes and xmm3 is 0x8081
+// the alphas end up in xmm0 and xmm1 and the pixels in xmm5 and xmm8, 2 pixels to a register
+unsafe fn blend_row_sse2(row: *mut FramebufferColor, alphas: &[u8], foreground: FramebufferColor) {
+    let vectors = alphas.len() / 4;
+    if vectors != 0 {
+        unsafe {
+            asm!(
+                "pxor xmm7, xmm7",
+                "pcmpeqw xmm6, xmm6",
+                "psrlw xmm6, 8",
+                "movd xmm4, {foreground:e}",
+                "pshufd xmm4, xmm4, 0",
+                "punpcklbw xmm4, xmm7",
+                "mov {foreground:e}, 0x80818081",
+                "movd xmm3, {foreground:e}",
+                "pshufd xmm3, xmm3, 0",
+                "2:",
+                // each alpha repeated over the 4 bytes of its pixel
+                "movd xmm0, [{alphas}]",
+                "punpcklbw xmm0, xmm0",
+                "punpcklwd xmm0, xmm0",
+                "movdqa xmm1, xmm0",
+                "punpcklbw xmm0, xmm7",
+                "punpckhbw xmm1, xmm7",
+                "movdqu xmm5, [{row}]",
+                "movdqa xmm8, xmm5",
+                "punpcklbw xmm5, xmm7",
+                "punpckhbw xmm8, xmm7",
+                "movdqa xmm2, xmm6",
+                "psubw xmm2, xmm0",
+                "pmullw xmm2, xmm5",
+                "pmullw xmm0, xmm4",
+                "pmulhuw xmm2, xmm3",
+                "psrlw xmm2, 7",
+                "pmulhuw xmm0, xmm3",
+                "psrlw xmm0, 7",
+                "paddw xmm0, xmm2",
+                "movdqa xmm2, xmm6",
+                "psubw xmm2, xmm1",
+                "pmullw xmm2, xmm8",
+                "pmullw xmm1, xmm4",
+                "pmulhuw xmm2, xmm3",
+                "psrlw xmm2, 7",
+                "pmulhuw xmm1, xmm3",
+                "psrlw xmm1, 7",
+                "paddw xmm1, xmm2",
+                "packuswb xmm0, xmm1",
+                "movdqu [{row}], xmm0",
+                "add {alphas}, 4",
+                "add {row}, 16",
+                "dec {vectors}",
+                "jnz 2b",
+                row = inout(reg) row => _,
+                alphas = inout(reg) alphas.as_ptr() => _,
+                vectors = inout(reg) vectors => _,
+                foreground = inout(reg) foreground.0 => _,
+                out("xmm0") _,
+                out("xmm1") _,
+                out("xmm2") _,
+                out("xmm3") _,
+                out("xmm4") _,
+                out("xmm5") _,
+                out("xmm6") _,
+                out("xmm7") _,
+                out("xmm8") _,
+                options(nostack)
+            );
+        }
+    }
+    let done = vectors * 4;
+    unsafe { blend_row_scalar(row.add(done), &alphas[done..], foreground) };
+}
+
+// same registers as `blend_row_sse2`, with ymm9 used to spread each alpha over its pixel
+unsafe fn blend_row_avx2(row: *mut FramebufferColor, alphas: &[u8], foreground: FramebufferColor) {
+    let vectors = alphas.len() / 8;
+    if vectors != 0 {
+        unsafe {
+            asm!(
+                "vpxor ymm7, ymm7, ymm7",
+                "vpcmpeqw ymm6, ymm6, ymm6",
+                "vpsrlw ymm6, ymm6, 8",
+                "vmovd xmm4, {foreground:e}",
+                "vpbroadcastd ymm4, xmm4",
+                "vpunpcklbw ymm4, ymm4, ymm7",
+                "mov {foreground:e}, 0x80818081",
+                "vmovd xmm3, {foreground:e}",
+                "vpbroadcastd ymm3, xmm3",
+                "mov {foreground:e}, 0x01010101",
+                "vmovd xmm9, {foreground:e}",
+                "vpbroadcastd ymm9, xmm9",
+                "2:",
+                // unpacking works within each 128 bit half, so packing puts the pixels back in order
+                "vpmovzxbd ymm0, qword ptr [{alphas}]",
+                "vpmulld ymm0, ymm0, ymm9",
+                "vpunpckhbw ymm1, ymm0, ymm7",
+                "vpunpcklbw ymm0, ymm0, ymm7",
+                "vmovdqu ymm5, [{row}]",
+                "vpunpckhbw ymm8, ymm5, ymm7",
+                "vpunpcklbw ymm5, ymm5, ymm7",
+                "vpsubw ymm2, ymm6, ymm0",
+                "vpmullw ymm2, ymm2, ymm5",
+                "vpmullw ymm0, ymm0, ymm4",
+                "vpmulhuw ymm2, ymm2, ymm3",
+                "vpsrlw ymm2, ymm2, 7",
+                "vpmulhuw ymm0, ymm0, ymm3",
+                "vpsrlw ymm0, ymm0, 7",
+                "vpaddw ymm0, ymm0, ymm2",
+                "vpsubw ymm2, ymm6, ymm1",
+                "vpmullw ymm2, ymm2, ymm8",
+                "vpmullw ymm1, ymm1, ymm4",
+                "vpmulhuw ymm2, ymm2, ymm3",
+                "vpsrlw ymm2, ymm2, 7",
+                "vpmulhuw ymm1, ymm1, ymm3",
+                "vpsrlw ymm1, ymm1, 7",
+                "vpaddw ymm1, ymm1, ymm2",
+                "vpackuswb ymm0, ymm0, ymm1",
+                "vmovdqu [{row}], ymm0",
+                "add {alphas}, 8",
+                "add {row}, 32",
+                "dec {vectors}",
+                "jnz 2b",
+                "vzeroupper",
+                row = inout(reg) row => _,
+                alphas = inout(reg) alphas.as_ptr() => _,
+                vectors = inout(reg) vectors => _,
+                foreground = inout(reg) foreground.0 => _,
+                out("xmm0") _,
+                out("xmm1") _,
+                out("xmm2") _,
+                out("xmm3") _,
+                out("xmm4") _,
+                out("xmm5") _,
+                out("xmm6") _,
+                out("xmm7") _,
+                out("xmm8") _,
+                out("xmm9") _,
+                options(nostack)
+            );
+        }
+    }
+    let done = vectors * 8;
+    unsafe { blend_row_scalar(row.add(done), &alphas[done..], foreground) };
+}
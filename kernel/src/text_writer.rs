@@ -0,0 +1,81 @@
+use crate::{
+    framebuffer::Color,
+    screen::{AlphaMask, Screen},
+};
+use core::fmt::Write;
+use font::{Font, Layout};
+
+pub struct TextWriter<'a> {
+    pub x: &'a mut usize,
+    pub y: &'a mut usize,
+    pub left_margin: usize,
+    pub text_color: Color,
+    // filled in behind each glyph, `None` draws straight over whatever is already there
+    pub background: Option<Color>,
+    pub font: &'a Font<'a>,
+    pub screen: &'a mut dyn Screen,
+    // the last character written on this line, for kerning the next one against
+    pub previous: Option<char>,
+}
+
+impl TextWriter<'_> {
+    // draws the lines of a layout made with the same font, with its top left where the writer is,
+    // and leaves the writer at the start of the line below it
+    pub fn write_layout(&mut self, layout: &Layout<'_>) {
+        let left = *self.x;
+        let top = *self.y;
+        for line in layout.lines() {
+            *self.x = left + line.x;
+            *self.y = top + line.y;
+            self.previous = None;
+            _ = self.write_str(line.text);
+        }
+        *self.x = self.left_margin;
+        *self.y = top + layout.height();
+        self.previous = None;
+    }
+}
+
+impl Write for TextWriter<'_> {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        s.chars().try_for_each(|c| self.write_char(c))
+    }
+
+    fn write_char(&mut self, c: char) -> core::fmt::Result {
+        if let Some(char) = self.font.char(c) {
+            if let Some(previous) = self.previous {
+                *self.x = self
+                    .x
+                    .saturating_add_signed(self.font.kerning(previous, c) as isize);
+            }
+
+            let page = &self.font.pages[char.page as usize];
+
+            let mask = AlphaMask {
+                alphas: &page.brightnesses
+                    [char.x as usize + char.y as usize * page.width as usize..],
+                width: char.width as usize,
+                height: char.height as usize,
+                stride: page.width as usize,
+            };
+            let left = self.x.saturating_add_signed(char.xoffset as isize);
+            let top = self.y.saturating_add_signed(char.yoffset as isize);
+            if let Some(background) = self.background {
+                self.screen
+                    .fill(left, top, mask.width, mask.height, background);
+            }
+            self.screen.blend_mask(left, top, mask, self.text_color);
+
+            *self.x += char.xadvance as usize;
+            self.previous = Some(c);
+        }
+
+        if c == '\n' {
+            *self.x = self.left_margin;
+            *self.y += self.font.common.line_height as usize;
+            self.previous = None;
+        }
+
+        Ok(())
+    }
+}
@@ -1,5 +1,5 @@
 use crate::{
-    framebuffer::{Color, FramebufferColor, framebuffer},
+    framebuffer::{Color, framebuffer},
     text_writer::TextWriter,
 };
 use core::arch::asm;
@@ -9,12 +9,13 @@ pub fn hlt() {
     unsafe { asm!("hlt", options(nomem, nostack)) };
 }
 
+// through dx, since an immediate port number only reaches up to 0xFF
 pub unsafe fn inb<const PORT: u16>() -> u8 {
     let value;
     unsafe {
         asm!(
-            "in al, {PORT}",
-            PORT = const PORT,
+            "in al, dx",
+            in("dx") PORT,
             out("al") value,
             options(nomem, nostack)
         );
@@ -25,8 +26,8 @@ pub unsafe fn inb<const PORT: u16>() -> u8 {
 pub unsafe fn outb<const PORT: u16>(value: u8) {
     unsafe {
         asm!(
-            "out {PORT}, al",
-            PORT = const PORT,
+            "out dx, al",
+            in("dx") PORT,
             in("al") value,
             options(nomem, nostack)
         );
@@ -37,6 +38,19 @@ pub fn io_wait() {
     unsafe { outb::<0x80>(0) };
 }
 
+pub fn rdtsc() -> u64 {
+    let (low, high): (u32, u32);
+    unsafe {
+        asm!(
+            "rdtsc",
+            out("eax") low,
+            out("edx") high,
+            options(nomem, nostack)
+        );
+    }
+    (high as u64) << 32 | low as u64
+}
+
 pub fn get_flags() -> u64 {
     let flags;
     unsafe {
@@ -50,6 +64,32 @@ pub fn get_flags() -> u64 {
     flags
 }
 
+pub unsafe fn rdmsr(msr: u32) -> u64 {
+    let (low, high): (u32, u32);
+    unsafe {
+        asm!(
+            "rdmsr",
+            in("ecx") msr,
+            out("eax") low,
+            out("edx") high,
+            options(nomem, nostack)
+        );
+    }
+    (high as u64) << 32 | low as u64
+}
+
+pub unsafe fn wrmsr(msr: u32, value: u64) {
+    unsafe {
+        asm!(
+            "wrmsr",
+            in("ecx") msr,
+            in("eax") value as u32,
+            in("edx") (value >> 32) as u32,
+            options(nostack)
+        );
+    }
+}
+
 pub fn error_screen<R>(f: impl FnOnce(&mut TextWriter<'_>) -> R) -> R {
     let mut framebuffer = framebuffer();
 
@@ -59,7 +99,7 @@ pub fn error_screen<R>(f: impl FnOnce(&mut TextWriter<'_>) -> R) -> R {
         0,
         framebuffer.width(),
         framebuffer.height(),
-        FramebufferColor::new(background),
+        framebuffer.layout().encode(background),
     );
 
     let mut text_writer = TextWriter {
@@ -71,9 +111,10 @@ pub fn error_screen<R>(f: impl FnOnce(&mut TextWriter<'_>) -> R) -> R {
             g: 255,
             b: 255,
         },
-        background,
+        background: Some(background),
         font: &SPACE_MONO,
         screen: &mut framebuffer,
+        previous: None,
     };
 
     f(&mut text_writer)
@@ -0,0 +1,406 @@
+use crate::{
+    framebuffer::{Color, Rgba},
+    screen::{AlphaMask, Rect, Screen},
+};
+use alloc::vec;
+use core::str::FromStr;
+
+// a rectangle of another screen as a screen of its own, with its own origin and clipped to its bounds
+pub struct ScreenView<'a, S: Screen + ?Sized> {
+    screen: &'a mut S,
+    rect: Rect,
+}
+
+impl<'a, S: Screen + ?Sized> ScreenView<'a, S> {
+    // the rect gets clipped to the screen underneath
+    pub fn new(screen: &'a mut S, rect: Rect) -> Self {
+        let rect = rect.intersection(&Rect::new(0, 0, screen.width(), screen.height()));
+        Self { screen, rect }
+    }
+
+    // where the view is on the screen underneath
+    pub fn rect(&self) -> Rect {
+        self.rect
+    }
+
+    fn bounds(&self) -> Rect {
+        Rect::new(0, 0, self.rect.width, self.rect.height)
+    }
+}
+
+impl<S: Screen + ?Sized> Screen for ScreenView<'_, S> {
+    fn width(&self) -> usize {
+        self.rect.width
+    }
+
+    fn height(&self) -> usize {
+        self.rect.height
+    }
+
+    unsafe fn set_pixel_unchecked(&mut self, x: usize, y: usize, color: Color) {
+        unsafe {
+            self.screen
+                .set_pixel_unchecked(x + self.rect.left, y + self.rect.top, color)
+        };
+    }
+
+    unsafe fn get_pixel_unchecked(&self, x: usize, y: usize) -> Color {
+        unsafe {
+            self.screen
+                .get_pixel_unchecked(x + self.rect.left, y + self.rect.top)
+        }
+    }
+
+    // the rest are clipped here and passed on, so the screen underneath can use its fast paths
+
+    fn fill(&mut self, left: usize, top: usize, width: usize, height: usize, color: Color) {
+        let rect = Rect::new(left, top, width, height).intersection(&self.bounds());
+        if !rect.is_empty() {
+            self.screen.fill(
+                rect.left + self.rect.left,
+                rect.top + self.rect.top,
+                rect.width,
+                rect.height,
+                color,
+            );
+        }
+    }
+
+    fn fill_blended(&mut self, left: usize, top: usize, width: usize, height: usize, color: Rgba) {
+        let rect = Rect::new(left, top, width, height).intersection(&self.bounds());
+        if !rect.is_empty() {
+            self.screen.fill_blended(
+                rect.left + self.rect.left,
+                rect.top + self.rect.top,
+                rect.width,
+                rect.height,
+                color,
+            );
+        }
+    }
+
+    fn blend_mask(&mut self, left: usize, top: usize, mask: AlphaMask<'_>, color: Color) {
+        if left >= self.rect.width || top >= self.rect.height {
+            return;
+        }
+        let mask = AlphaMask {
+            width: mask.width.min(self.rect.width - left),
+            height: mask.height.min(self.rect.height - top),
+            ..mask
+        };
+        self.screen
+            .blend_mask(left + self.rect.left, top + self.rect.top, mask, color);
+    }
+}
+
+// every pixel drawn to it becomes a `scale` by `scale` block on the screen underneath
+pub struct ScaledScreen<'a, S: Screen + ?Sized> {
+    screen: &'a mut S,
+    scale: usize,
+}
+
+impl<'a, S: Screen + ?Sized> ScaledScreen<'a, S> {
+    pub fn new(screen: &'a mut S, scale: usize) -> Self {
+        assert!(scale != 0);
+        Self { screen, scale }
+    }
+}
+
+impl<S: Screen + ?Sized> Screen for ScaledScreen<'_, S> {
+    fn width(&self) -> usize {
+        self.screen.width() / self.scale
+    }
+
+    fn height(&self) -> usize {
+        self.screen.height() / self.scale
+    }
+
+    unsafe fn set_pixel_unchecked(&mut self, x: usize, y: usize, color: Color) {
+        self.screen.fill(
+            x * self.scale,
+            y * self.scale,
+            self.scale,
+            self.scale,
+            color,
+        );
+    }
+
+    unsafe fn get_pixel_unchecked(&self, x: usize, y: usize) -> Color {
+        unsafe {
+            self.screen
+                .get_pixel_unchecked(x * self.scale, y * self.scale)
+        }
+    }
+
+    fn fill(&mut self, left: usize, top: usize, width: usize, height: usize, color: Color) {
+        let rect = Rect::new(left, top, width, height).intersection(&Rect::new(
+            0,
+            0,
+            self.width(),
+            self.height(),
+        ));
+        self.screen.fill(
+            rect.left * self.scale,
+            rect.top * self.scale,
+            rect.width * self.scale,
+            rect.height * self.scale,
+            color,
+        );
+    }
+
+    fn fill_blended(&mut self, left: usize, top: usize, width: usize, height: usize, color: Rgba) {
+        let rect = Rect::new(left, top, width, height).intersection(&Rect::new(
+            0,
+            0,
+            self.width(),
+            self.height(),
+        ));
+        self.screen.fill_blended(
+            rect.left * self.scale,
+            rect.top * self.scale,
+            rect.width * self.scale,
+            rect.height * self.scale,
+            color,
+        );
+    }
+}
+
+// how far everything drawn gets turned clockwise, set with `rotation=` on the command line
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rotation {
+    None,
+    Clockwise90,
+    Clockwise180,
+    Clockwise270,
+}
+
+impl FromStr for Rotation {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "0" => Ok(Self::None),
+            "90" => Ok(Self::Clockwise90),
+            "180" => Ok(Self::Clockwise180),
+            "270" => Ok(Self::Clockwise270),
+            _ => Err(()),
+        }
+    }
+}
+
+impl Rotation {
+    // the size things get drawn at, given the size of the screen underneath
+    pub const fn rotated_size(self, width: usize, height: usize) -> (usize, usize) {
+        match self {
+            Self::None | Self::Clockwise180 => (width, height),
+            Self::Clockwise90 | Self::Clockwise270 => (height, width),
+        }
+    }
+
+    // where a rect that was drawn ends up on the screen underneath, which is `width` by `height`
+    pub fn map_rect(self, rect: Rect, width: usize, height: usize) -> Rect {
+        let (rotated_width, rotated_height) = self.rotated_size(width, height);
+        let rect = rect.intersection(&Rect::new(0, 0, rotated_width, rotated_height));
+        if rect.is_empty() {
+            return Rect::new(0, 0, 0, 0);
+        }
+        let Rect {
+            left,
+            top,
+            width: rect_width,
+            height: rect_height,
+        } = rect;
+        match self {
+            Self::None => rect,
+            Self::Clockwise90 => {
+                Rect::new(width - top - rect_height, left, rect_height, rect_width)
+            }
+            Self::Clockwise180 => Rect::new(
+                width - left - rect_width,
+                height - top - rect_height,
+                rect_width,
+                rect_height,
+            ),
+            Self::Clockwise270 => {
+                Rect::new(top, height - left - rect_width, rect_height, rect_width)
+            }
+        }
+    }
+
+    // turns things back, so what `self` maps from a `width` by `height` screen can be mapped back
+    // with the rotated size of that screen
+    const fn inverse(self) -> Self {
+        match self {
+            Self::None => Self::None,
+            Self::Clockwise90 => Self::Clockwise270,
+            Self::Clockwise180 => Self::Clockwise180,
+            Self::Clockwise270 => Self::Clockwise90,
+        }
+    }
+
+    const fn map_point(self, x: usize, y: usize, width: usize, height: usize) -> (usize, usize) {
+        match self {
+            Self::None => (x, y),
+            Self::Clockwise90 => (width - 1 - y, x),
+            Self::Clockwise180 => (width - 1 - x, height - 1 - y),
+            Self::Clockwise270 => (y, height - 1 - x),
+        }
+    }
+}
+
+// for panels that are mounted on their side, or upside down
+pub struct RotatedScreen<'a, S: Screen + ?Sized> {
+    screen: &'a mut S,
+    rotation: Rotation,
+}
+
+impl<'a, S: Screen + ?Sized> RotatedScreen<'a, S> {
+    pub fn new(screen: &'a mut S, rotation: Rotation) -> Self {
+        Self { screen, rotation }
+    }
+}
+
+impl<S: Screen + ?Sized> Screen for RotatedScreen<'_, S> {
+    fn width(&self) -> usize {
+        self.rotation
+            .rotated_size(self.screen.width(), self.screen.height())
+            .0
+    }
+
+    fn height(&self) -> usize {
+        self.rotation
+            .rotated_size(self.screen.width(), self.screen.height())
+            .1
+    }
+
+    unsafe fn set_pixel_unchecked(&mut self, x: usize, y: usize, color: Color) {
+        let (x, y) = self
+            .rotation
+            .map_point(x, y, self.screen.width(), self.screen.height());
+        unsafe { self.screen.set_pixel_unchecked(x, y, color) };
+    }
+
+    unsafe fn get_pixel_unchecked(&self, x: usize, y: usize) -> Color {
+        let (x, y) = self
+            .rotation
+            .map_point(x, y, self.screen.width(), self.screen.height());
+        unsafe { self.screen.get_pixel_unchecked(x, y) }
+    }
+
+    fn fill(&mut self, left: usize, top: usize, width: usize, height: usize, color: Color) {
+        let rect = self.rotation.map_rect(
+            Rect::new(left, top, width, height),
+            self.screen.width(),
+            self.screen.height(),
+        );
+        self.screen
+            .fill(rect.left, rect.top, rect.width, rect.height, color);
+    }
+
+    fn fill_blended(&mut self, left: usize, top: usize, width: usize, height: usize, color: Rgba) {
+        let rect = self.rotation.map_rect(
+            Rect::new(left, top, width, height),
+            self.screen.width(),
+            self.screen.height(),
+        );
+        self.screen
+            .fill_blended(rect.left, rect.top, rect.width, rect.height, color);
+    }
+
+    fn copy(&mut self, screen: &dyn Screen, left: usize, top: usize) {
+        if self.rotation == Rotation::None {
+            self.screen.copy(screen, left, top);
+            return;
+        }
+
+        let rect = self.rotation.map_rect(
+            Rect::new(left, top, screen.width(), screen.height()),
+            self.screen.width(),
+            self.screen.height(),
+        );
+        let (width, height) = (self.width(), self.height());
+        let source = RotatedSource {
+            screen,
+            rotation: self.rotation.inverse(),
+            rect,
+            origin: (left, top),
+            rotated_size: (width, height),
+        };
+        self.screen.copy(&source, rect.left, rect.top);
+    }
+
+    // text is drawn through here even when nothing is rotated, so that goes straight to the screen's own blending,
+    // otherwise the mask is turned to match and blended in one go
+    fn blend_mask(&mut self, left: usize, top: usize, mask: AlphaMask<'_>, color: Color) {
+        if self.rotation == Rotation::None {
+            self.screen.blend_mask(left, top, mask, color);
+            return;
+        }
+
+        let (width, height) = (self.screen.width(), self.screen.height());
+        let drawn = Rect::new(left, top, mask.width, mask.height).intersection(&Rect::new(
+            0,
+            0,
+            self.width(),
+            self.height(),
+        ));
+        let rect = self.rotation.map_rect(drawn, width, height);
+        if rect.is_empty() {
+            return;
+        }
+
+        let mut alphas = vec![0; rect.area()];
+        for y in drawn.top..drawn.bottom() {
+            let row = mask.row(y - top);
+            for x in drawn.left..drawn.right() {
+                let (mapped_x, mapped_y) = self.rotation.map_point(x, y, width, height);
+                alphas[mapped_x - rect.left + (mapped_y - rect.top) * rect.width] = row[x - left];
+            }
+        }
+        let mask = AlphaMask {
+            alphas: &alphas,
+            width: rect.width,
+            height: rect.height,
+            stride: rect.width,
+        };
+        self.screen.blend_mask(rect.left, rect.top, mask, color);
+    }
+}
+
+// what gets copied to a `RotatedScreen`, as it looks from the screen underneath
+// covering just `rect` of that screen
+struct RotatedSource<'a> {
+    screen: &'a dyn Screen,
+    // the inverse of the `RotatedScreen`'s rotation
+    rotation: Rotation,
+    rect: Rect,
+    // where `screen` was copied to, before rotating
+    origin: (usize, usize),
+    rotated_size: (usize, usize),
+}
+
+impl Screen for RotatedSource<'_> {
+    fn width(&self) -> usize {
+        self.rect.width
+    }
+
+    fn height(&self) -> usize {
+        self.rect.height
+    }
+
+    unsafe fn set_pixel_unchecked(&mut self, _x: usize, _y: usize, _color: Color) {
+        unreachable!("copies only read from their source");
+    }
+
+    unsafe fn get_pixel_unchecked(&self, x: usize, y: usize) -> Color {
+        let (rotated_width, rotated_height) = self.rotated_size;
+        let (x, y) = self.rotation.map_point(
+            x + self.rect.left,
+            y + self.rect.top,
+            rotated_width,
+            rotated_height,
+        );
+        let (left, top) = self.origin;
+        unsafe { self.screen.get_pixel_unchecked(x - left, y - top) }
+    }
+}
@@ -0,0 +1,422 @@
+use crate::{
+    cpuid::cpuid,
+    framebuffer::framebuffer,
+    interrupt_safe_mutex::InterruptSafeMutex,
+    page_allocator::PAGE_ALLOCATOR,
+    utils::{rdmsr, wrmsr},
+};
+use core::{alloc::Layout, arch::asm, mem::MaybeUninit};
+
+pub const PAGE_SIZE: usize = 4096;
+pub const LARGE_PAGE_SIZE: usize = 512 * PAGE_SIZE;
+
+const PRESENT: u64 = 1 << 0;
+const WRITABLE: u64 = 1 << 1;
+const USER: u64 = 1 << 2;
+const WRITE_THROUGH: u64 = 1 << 3;
+const CACHE_DISABLE: u64 = 1 << 4;
+const LARGE: u64 = 1 << 7;
+const PAT: u64 = 1 << 7;
+const LARGE_PAT: u64 = 1 << 12;
+const NO_EXECUTE: u64 = 1 << 63;
+const ADDRESS_MASK: u64 = 0x000F_FFFF_FFFF_F000;
+const LARGE_ADDRESS_MASK: u64 = 0x000F_FFFF_FFE0_0000;
+
+const IA32_PAT: u32 = 0x277;
+const IA32_EFER: u32 = 0xC000_0080;
+const EFER_NXE: u64 = 1 << 11;
+
+// the power-on default, except entry 4 is write combining instead of write back
+const PAT_VALUE: u64 = 0x0007_0401_0007_0406;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CacheMode {
+    WriteBack,
+    WriteThrough,
+    WriteCombining,
+    Uncached,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Protection {
+    pub writable: bool,
+    pub executable: bool,
+    pub user: bool,
+    pub cache_mode: CacheMode,
+}
+
+impl Protection {
+    pub const KERNEL_CODE: Self = Self {
+        writable: true,
+        executable: true,
+        user: false,
+        cache_mode: CacheMode::WriteBack,
+    };
+    pub const KERNEL_DATA: Self = Self {
+        writable: true,
+        executable: false,
+        user: false,
+        cache_mode: CacheMode::WriteBack,
+    };
+    pub const MMIO: Self = Self {
+        writable: true,
+        executable: false,
+        user: false,
+        cache_mode: CacheMode::Uncached,
+    };
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MapError {
+    OutOfMemory,
+    AlreadyMapped,
+    NotMapped,
+}
+
+#[derive(Clone, Copy)]
+#[repr(transparent)]
+struct Entry(u64);
+
+impl Entry {
+    fn leaf(address: usize, protection: Protection, large: bool, no_execute: bool) -> Self {
+        let mut bits = address as u64 | PRESENT;
+        if large {
+            bits |= LARGE;
+        }
+        if protection.writable {
+            bits |= WRITABLE;
+        }
+        if protection.user {
+            bits |= USER;
+        }
+        if !protection.executable && no_execute {
+            bits |= NO_EXECUTE;
+        }
+        bits |= match protection.cache_mode {
+            CacheMode::WriteBack => 0,
+            CacheMode::WriteThrough => WRITE_THROUGH,
+            CacheMode::Uncached => CACHE_DISABLE | WRITE_THROUGH,
+            CacheMode::WriteCombining if large => LARGE_PAT,
+            CacheMode::WriteCombining => PAT,
+        };
+        Self(bits)
+    }
+
+    fn is_present(self) -> bool {
+        self.0 & PRESENT != 0
+    }
+
+    // only meaningful for pdpt and pd entries, on pt entries this bit is PAT
+    fn is_large(self) -> bool {
+        self.0 & (PRESENT | LARGE) == PRESENT | LARGE
+    }
+
+    fn address(self, large: bool) -> usize {
+        let mask = if large {
+            LARGE_ADDRESS_MASK
+        } else {
+            ADDRESS_MASK
+        };
+        (self.0 & mask) as usize
+    }
+}
+
+#[repr(C, align(4096))]
+struct PageTable {
+    entries: [Entry; 512],
+}
+
+const _: () = assert!(size_of::<PageTable>() == PAGE_SIZE);
+
+// all physical memory is identity mapped, so tables can be accessed through their physical address
+unsafe fn table(address: usize) -> &'static mut PageTable {
+    unsafe { &mut *core::ptr::with_exposed_provenance_mut(address) }
+}
+
+fn index(virtual_address: usize, level: u32) -> usize {
+    (virtual_address >> (12 + 9 * level)) & 0x1FF
+}
+
+fn allocate_table() -> Result<usize, MapError> {
+    let address = PAGE_ALLOCATOR
+        .with(|alloc| alloc.allocate(Layout::new::<PageTable>()))
+        .ok_or(MapError::OutOfMemory)?;
+    unsafe { core::ptr::write_bytes(table(address), 0, 1) };
+    Ok(address)
+}
+
+fn next_table(entry: &mut Entry, create: bool) -> Result<&'static mut PageTable, MapError> {
+    if !entry.is_present() {
+        if !create {
+            return Err(MapError::NotMapped);
+        }
+        // permissions are only restricted at the leaf entries
+        *entry = Entry(allocate_table()? as u64 | PRESENT | WRITABLE | USER);
+    }
+    assert!(!entry.is_large(), "huge pages are not supported");
+    Ok(unsafe { table(entry.address(false)) })
+}
+
+fn split_large_page(entry: &mut Entry) -> Result<(), MapError> {
+    let table_address = allocate_table()?;
+    let pt = unsafe { table(table_address) };
+
+    let base = entry.address(true);
+    let pat = if entry.0 & LARGE_PAT != 0 { PAT } else { 0 };
+    let flags = entry.0 & !(ADDRESS_MASK | LARGE) | pat;
+    for (i, pt_entry) in pt.entries.iter_mut().enumerate() {
+        *pt_entry = Entry((base + i * PAGE_SIZE) as u64 | flags);
+    }
+
+    *entry = Entry(table_address as u64 | PRESENT | WRITABLE | USER);
+    Ok(())
+}
+
+fn read_cr3() -> usize {
+    let cr3: usize;
+    unsafe { asm!("mov {}, cr3", out(reg) cr3, options(nomem, nostack)) };
+    cr3
+}
+
+unsafe fn invlpg(virtual_address: usize) {
+    unsafe { asm!("invlpg [{}]", in(reg) virtual_address, options(nostack)) };
+}
+
+pub struct VirtualMemory {
+    pml4: usize,
+    no_execute: bool,
+}
+
+impl VirtualMemory {
+    pub fn new() -> Result<Self, MapError> {
+        Ok(Self {
+            pml4: allocate_table()?,
+            no_execute: unsafe { rdmsr(IA32_EFER) } & EFER_NXE != 0,
+        })
+    }
+
+    pub fn pml4(&self) -> usize {
+        self.pml4
+    }
+
+    pub fn is_active(&self) -> bool {
+        read_cr3() & ADDRESS_MASK as usize == self.pml4
+    }
+
+    pub unsafe fn activate(&self) {
+        unsafe { asm!("mov cr3, {}", in(reg) self.pml4, options(nostack)) };
+    }
+
+    fn pd_entry(&mut self, virtual_address: usize, create: bool) -> Result<&mut Entry, MapError> {
+        let pml4 = unsafe { table(self.pml4) };
+        let pdpt = next_table(&mut pml4.entries[index(virtual_address, 3)], create)?;
+        let pd = next_table(&mut pdpt.entries[index(virtual_address, 2)], create)?;
+        Ok(&mut pd.entries[index(virtual_address, 1)])
+    }
+
+    pub fn translate(&self, virtual_address: usize) -> Option<usize> {
+        let mut entry = Entry(self.pml4 as u64 | PRESENT);
+        for level in (0..4).rev() {
+            let table = unsafe { table(entry.address(false)) };
+            entry = table.entries[index(virtual_address, level)];
+            if !entry.is_present() {
+                return None;
+            }
+            if level == 1 && entry.is_large() {
+                return Some(entry.address(true) + virtual_address % LARGE_PAGE_SIZE);
+            }
+        }
+        Some(entry.address(false) + virtual_address % PAGE_SIZE)
+    }
+
+    pub unsafe fn map(
+        &mut self,
+        virtual_address: usize,
+        physical_address: usize,
+        size: usize,
+        protection: Protection,
+    ) -> Result<(), MapError> {
+        assert!(virtual_address.is_multiple_of(PAGE_SIZE));
+        assert!(physical_address.is_multiple_of(PAGE_SIZE));
+
+        let no_execute = self.no_execute;
+        let mut offset = 0;
+        while offset < size {
+            let virtual_address = virtual_address + offset;
+            let physical_address = physical_address + offset;
+            let large = virtual_address.is_multiple_of(LARGE_PAGE_SIZE)
+                && physical_address.is_multiple_of(LARGE_PAGE_SIZE)
+                && size - offset >= LARGE_PAGE_SIZE;
+
+            let pd_entry = self.pd_entry(virtual_address, true)?;
+            let entry = if large {
+                pd_entry
+            } else if pd_entry.is_large() {
+                return Err(MapError::AlreadyMapped);
+            } else {
+                &mut next_table(pd_entry, true)?.entries[index(virtual_address, 0)]
+            };
+
+            if entry.is_present() {
+                return Err(MapError::AlreadyMapped);
+            }
+            *entry = Entry::leaf(physical_address, protection, large, no_execute);
+
+            offset += if large { LARGE_PAGE_SIZE } else { PAGE_SIZE };
+        }
+        Ok(())
+    }
+
+    fn update_leaves(
+        &mut self,
+        virtual_address: usize,
+        size: usize,
+        mut f: impl FnMut(&mut Entry, bool),
+    ) -> Result<(), MapError> {
+        assert!(virtual_address.is_multiple_of(PAGE_SIZE));
+
+        let active = self.is_active();
+        let mut offset = 0;
+        while offset < size {
+            let virtual_address = virtual_address + offset;
+
+            let pd_entry = self.pd_entry(virtual_address, false)?;
+            if pd_entry.is_large() {
+                if virtual_address.is_multiple_of(LARGE_PAGE_SIZE)
+                    && size - offset >= LARGE_PAGE_SIZE
+                {
+                    f(pd_entry, true);
+                    if active {
+                        unsafe { invlpg(virtual_address) };
+                    }
+                    offset += LARGE_PAGE_SIZE;
+                    continue;
+                }
+                split_large_page(pd_entry)?;
+            }
+
+            let entry = &mut next_table(pd_entry, false)?.entries[index(virtual_address, 0)];
+            if !entry.is_present() {
+                return Err(MapError::NotMapped);
+            }
+            f(entry, false);
+            if active {
+                unsafe { invlpg(virtual_address) };
+            }
+            offset += PAGE_SIZE;
+        }
+        Ok(())
+    }
+
+    pub unsafe fn unmap(&mut self, virtual_address: usize, size: usize) -> Result<(), MapError> {
+        self.update_leaves(virtual_address, size, |entry, _| *entry = Entry(0))
+    }
+
+    pub unsafe fn protect(
+        &mut self,
+        virtual_address: usize,
+        size: usize,
+        protection: Protection,
+    ) -> Result<(), MapError> {
+        let no_execute = self.no_execute;
+        self.update_leaves(virtual_address, size, |entry, large| {
+            *entry = Entry::leaf(entry.address(large), protection, large, no_execute);
+        })
+    }
+}
+
+pub static VIRTUAL_MEMORY: InterruptSafeMutex<VirtualMemory> =
+    InterruptSafeMutex::new(VirtualMemory {
+        pml4: 0,
+        no_execute: false,
+    });
+
+pub unsafe fn init_virtual_memory(memory_map: &efi::MemoryMap) {
+    assert!(
+        unsafe { cpuid(1, MaybeUninit::uninit()) }.edx & (1 << 16) != 0,
+        "the cpu should support PAT"
+    );
+    unsafe { wrmsr(IA32_PAT, PAT_VALUE) };
+
+    let max_extended_cpuid = unsafe { cpuid(0x80000000, MaybeUninit::uninit()).eax };
+    if max_extended_cpuid >= 0x80000001
+        && unsafe { cpuid(0x80000001, MaybeUninit::uninit()) }.edx & (1 << 20) != 0
+    {
+        unsafe { wrmsr(IA32_EFER, rdmsr(IA32_EFER) | EFER_NXE) };
+    }
+
+    let mut virtual_memory = VirtualMemory::new().expect("allocating the pml4 should succeed");
+
+    for memory_descriptor in memory_map {
+        let mut protection = match memory_descriptor.memory_type {
+            efi::MemoryType::LoaderCode
+            | efi::MemoryType::BootServicesCode
+            | efi::MemoryType::RuntimeServicesCode
+            | boot_info::KERNEL_CODE => Protection::KERNEL_CODE,
+            efi::MemoryType::MemoryMappedIO | efi::MemoryType::MemoryMappedIOPortSpace => {
+                Protection::MMIO
+            }
+            _ => Protection::KERNEL_DATA,
+        };
+        if !memory_descriptor
+            .attribute
+            .contains(efi::MemoryAttribute::WB)
+            && memory_descriptor
+                .attribute
+                .contains(efi::MemoryAttribute::UC)
+        {
+            protection.cache_mode = CacheMode::Uncached;
+        }
+
+        unsafe {
+            virtual_memory
+                .map(
+                    memory_descriptor.physical_start,
+                    memory_descriptor.physical_start,
+                    memory_descriptor.size(),
+                    protection,
+                )
+                .expect("identity mapping the memory map should succeed");
+        }
+    }
+
+    {
+        let framebuffer = framebuffer();
+        let start = framebuffer.base() / PAGE_SIZE * PAGE_SIZE;
+        let end = (framebuffer.base() + framebuffer.size()).next_multiple_of(PAGE_SIZE);
+        let protection = Protection {
+            cache_mode: CacheMode::WriteCombining,
+            ..Protection::MMIO
+        };
+
+        // the framebuffer may be part of the memory map, not in it at all, or only partly in it,
+        // so each run of pages that is already mapped gets protected and the rest gets mapped
+        let mut run_start = start;
+        while run_start < end {
+            let mapped = virtual_memory.translate(run_start).is_some();
+            let mut run_end = run_start + PAGE_SIZE;
+            while run_end < end && virtual_memory.translate(run_end).is_some() == mapped {
+                run_end += PAGE_SIZE;
+            }
+
+            unsafe {
+                if mapped {
+                    virtual_memory.protect(run_start, run_end - run_start, protection)
+                } else {
+                    virtual_memory.map(run_start, run_start, run_end - run_start, protection)
+                }
+                .expect("mapping the framebuffer should succeed");
+            }
+            run_start = run_end;
+        }
+    }
+
+    // leave the null page unmapped so null pointer accesses fault
+    if virtual_memory.translate(0).is_some() {
+        unsafe { virtual_memory.unmap(0, PAGE_SIZE).unwrap() };
+    }
+
+    unsafe { virtual_memory.activate() };
+
+    VIRTUAL_MEMORY.with(|vm| *vm = virtual_memory);
+}
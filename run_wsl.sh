@@ -7,6 +7,10 @@ cp /usr/share/OVMF/OVMF_VARS.fd .
 
 mkdir -p esp/efi/boot
 cp target/x86_64-unknown-uefi/debug/bootloader.efi esp/efi/boot/bootx64.efi
+cp target/x86_64-unknown-none/debug/kernel esp/efi/boot/kernel.elf
+if [ -f cmdline.txt ]; then
+    cp cmdline.txt esp/efi/boot/cmdline.txt
+fi
 
 cmd.exe /c qemu-system-x86_64 -m 256M \
     -drive if=pflash,format=raw,readonly=on,file=OVMF_CODE.fd \